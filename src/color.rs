@@ -0,0 +1,161 @@
+//! ANSI colouring of task listings.
+//!
+//! Colours are chosen by a [`Theme`], which can be overridden in the configuration file,
+//! and switched on or off by a [`ColorChoice`]. The `NO_COLOR` environment variable
+//! (<https://no-color.org>) disables colour unless it is forced with `--color always`.
+
+use crate::{Priority, Task};
+use serde::Deserialize;
+use std::env;
+use std::str::FromStr;
+
+/// Whether colour should be used, as selected with `--color`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Resolves the choice for an output stream; `Auto` colours terminals unless
+    /// `NO_COLOR` is set to a non-empty value.
+    pub fn enabled(&self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                is_terminal && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+            }
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            other => Err(format!("unknown color choice `{}`", other)),
+        }
+    }
+}
+
+/// A combination of ANSI SGR attributes, written in config files as space-separated
+/// words such as `"bold red"` or `"dim"`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Style {
+    codes: Vec<u8>,
+}
+
+impl Style {
+    /// Wraps `text` in this style's escape sequences; plain styles leave it untouched.
+    pub fn paint(&self, text: &str) -> String {
+        if self.codes.is_empty() {
+            return text.to_string();
+        }
+        let codes: Vec<String> = self.codes.iter().map(u8::to_string).collect();
+        format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text)
+    }
+
+    /// Combines two styles, applying `other`'s attributes after this one's.
+    pub fn and(&self, other: &Style) -> Style {
+        let mut codes = self.codes.clone();
+        codes.extend(&other.codes);
+        Style { codes }
+    }
+}
+
+impl FromStr for Style {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let codes = s
+            .split_whitespace()
+            .map(|word| match word {
+                "bold" => Ok(1),
+                "dim" => Ok(2),
+                "italic" => Ok(3),
+                "underline" => Ok(4),
+                "black" => Ok(30),
+                "red" => Ok(31),
+                "green" => Ok(32),
+                "yellow" => Ok(33),
+                "blue" => Ok(34),
+                "magenta" => Ok(35),
+                "cyan" => Ok(36),
+                "white" => Ok(37),
+                "bright_black" | "gray" | "grey" => Ok(90),
+                "bright_red" => Ok(91),
+                "bright_green" => Ok(92),
+                "bright_yellow" => Ok(93),
+                "bright_blue" => Ok(94),
+                "bright_magenta" => Ok(95),
+                "bright_cyan" => Ok(96),
+                "bright_white" => Ok(97),
+                other => Err(format!("unknown style `{}`", other)),
+            })
+            .collect::<Result<Vec<u8>, String>>()?;
+        Ok(Style { codes })
+    }
+}
+
+impl TryFrom<String> for Style {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// Styles applied to tasks depending on their state.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub overdue: Style,
+    pub due_today: Style,
+    pub high_priority: Style,
+    pub completed: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        let style = |spec: &str| spec.parse::<Style>().expect("built-in style is valid");
+        Theme {
+            overdue: style("red"),
+            due_today: style("yellow"),
+            high_priority: style("bold"),
+            completed: style("dim"),
+        }
+    }
+}
+
+impl Theme {
+    /// Returns the combined style for `task`: completed tasks are only dimmed, while open
+    /// tasks are coloured by their `overdue` and `due_today` flags and emboldened when of
+    /// high priority. A task due earlier today is overdue rather than due today.
+    pub fn style_for(&self, task: &Task) -> Style {
+        if task.completed {
+            return self.completed.clone();
+        }
+        let mut style = Style::default();
+        if task.overdue {
+            style = style.and(&self.overdue);
+        } else if task.due_today {
+            style = style.and(&self.due_today);
+        }
+        if matches!(task.priority, Priority::Highest | Priority::High) {
+            style = style.and(&self.high_priority);
+        }
+        style
+    }
+
+    /// Paints `text`, the rendering of `task`, with the task's style.
+    pub fn paint(&self, task: &Task, text: &str) -> String {
+        self.style_for(task).paint(text)
+    }
+}
@@ -0,0 +1,83 @@
+//! User configuration, read from a TOML file.
+//!
+//! The file is looked up at `$MARKTASK_CONFIG`, then `$XDG_CONFIG_HOME/marktask/config.toml`,
+//! then `~/.config/marktask/config.toml`. Every setting is optional:
+//!
+//! ```toml
+//! [colors]
+//! overdue = "bold red"
+//! due_today = "yellow"
+//! high_priority = "bold"
+//! completed = "dim"
+//!
+//! [markdown]
+//! syntax = "dataview"  # or "emoji", the default
+//!
+//! [urgency]
+//! due = 12.0
+//! ```
+
+use crate::color::Theme;
+use crate::error::{Error, Result};
+use crate::markdown::Syntax;
+use crate::urgency::UrgencyModel;
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub colors: Theme,
+    pub markdown: MarkdownConfig,
+    pub urgency: UrgencyModel,
+}
+
+/// Settings for the Markdown marktask writes, e.g. when importing tasks.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MarkdownConfig {
+    pub syntax: Syntax,
+}
+
+impl Config {
+    /// Parses a configuration from TOML text.
+    pub fn from_toml(text: &str) -> Result<Self> {
+        toml::from_str(text).map_err(|err| Error::Config(err.message().to_string()))
+    }
+
+    /// Reads the configuration at `path`.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))?;
+        toml::from_str(&text)
+            .map_err(|err| Error::Config(format!("{}: {}", path.display(), err.message())))
+    }
+
+    /// Loads the configuration from `path` if given, otherwise from the default location.
+    ///
+    /// A missing default file yields the default configuration; an explicitly
+    /// requested file must exist.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => Self::from_file(path),
+            None => match default_path() {
+                Some(path) if path.is_file() => Self::from_file(&path),
+                _ => Ok(Self::default()),
+            },
+        }
+    }
+}
+
+/// The configuration file used when none is given explicitly.
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("MARKTASK_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("marktask").join("config.toml"))
+}
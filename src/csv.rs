@@ -0,0 +1,285 @@
+//! Delimited (CSV and TSV) output for spreadsheets.
+//!
+//! CSV follows RFC 4180: a header row, CRLF line endings, and fields quoted when they
+//! contain the delimiter, a double quote or a line break. TSV cannot quote, so tabs and
+//! line breaks inside fields are replaced by spaces.
+
+use crate::dates::format_duration;
+use crate::error::{Error, Result};
+use crate::Task;
+use chrono::{NaiveDate, NaiveTime};
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+/// A task attribute that can be written as a column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Name,
+    Completed,
+    Created,
+    Due,
+    DueTime,
+    Scheduled,
+    ScheduledTime,
+    Start,
+    StartTime,
+    Done,
+    DoneTime,
+    Overdue,
+    NotStarted,
+    DueToday,
+    DueSoon,
+    Priority,
+    Recurrence,
+    Estimate,
+    Urgency,
+    Tags,
+    Contexts,
+    Id,
+    DependsOn,
+    Blocked,
+    BlockId,
+    Uid,
+    Links,
+    Notes,
+    File,
+    Line,
+    Parent,
+}
+
+impl Column {
+    /// Every column, in the order they are listed.
+    pub const ALL: [Column; 31] = [
+        Column::Name,
+        Column::Completed,
+        Column::Created,
+        Column::Due,
+        Column::DueTime,
+        Column::Scheduled,
+        Column::ScheduledTime,
+        Column::Start,
+        Column::StartTime,
+        Column::Done,
+        Column::DoneTime,
+        Column::Overdue,
+        Column::NotStarted,
+        Column::DueToday,
+        Column::DueSoon,
+        Column::Priority,
+        Column::Recurrence,
+        Column::Estimate,
+        Column::Urgency,
+        Column::Tags,
+        Column::Contexts,
+        Column::Id,
+        Column::DependsOn,
+        Column::Blocked,
+        Column::BlockId,
+        Column::Uid,
+        Column::Links,
+        Column::Notes,
+        Column::File,
+        Column::Line,
+        Column::Parent,
+    ];
+}
+
+/// Columns written when none are requested explicitly.
+pub const DEFAULT_COLUMNS: [Column; 8] = [
+    Column::Completed,
+    Column::Name,
+    Column::Due,
+    Column::Scheduled,
+    Column::Start,
+    Column::Priority,
+    Column::File,
+    Column::Line,
+];
+
+impl Column {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::Name => "name",
+            Column::Completed => "completed",
+            Column::Created => "created",
+            Column::Due => "due",
+            Column::DueTime => "due_time",
+            Column::Scheduled => "scheduled",
+            Column::ScheduledTime => "scheduled_time",
+            Column::Start => "start",
+            Column::StartTime => "start_time",
+            Column::Done => "done",
+            Column::DoneTime => "done_time",
+            Column::Overdue => "overdue",
+            Column::NotStarted => "not_started",
+            Column::DueToday => "due_today",
+            Column::DueSoon => "due_soon",
+            Column::Priority => "priority",
+            Column::Recurrence => "recurrence",
+            Column::Estimate => "estimate",
+            Column::Urgency => "urgency",
+            Column::Tags => "tags",
+            Column::Contexts => "contexts",
+            Column::Id => "id",
+            Column::DependsOn => "depends_on",
+            Column::Blocked => "blocked",
+            Column::BlockId => "block_id",
+            Column::Uid => "uid",
+            Column::Links => "links",
+            Column::Notes => "notes",
+            Column::File => "file",
+            Column::Line => "line",
+            Column::Parent => "parent",
+        }
+    }
+
+    /// Renders this column's value for `task`, using an empty string for missing values.
+    /// Dates are followed by their time of day, as on a task line, when they have one.
+    pub fn value(&self, task: &Task) -> String {
+        let date = |date: Option<NaiveDate>, time: Option<NaiveTime>| match (date, time) {
+            (Some(date), Some(time)) => {
+                format!("{} {}", date.format("%Y-%m-%d"), time.format("%H:%M"))
+            }
+            (Some(date), None) => date.format("%Y-%m-%d").to_string(),
+            (None, _) => String::new(),
+        };
+        let time = |time: Option<NaiveTime>| {
+            time.map(|t| t.format("%H:%M").to_string())
+                .unwrap_or_default()
+        };
+        match self {
+            Column::Name => task.name.clone(),
+            Column::Completed => task.completed.to_string(),
+            Column::Created => date(task.created, None),
+            Column::Due => date(task.due, task.due_time),
+            Column::DueTime => time(task.due_time),
+            Column::Scheduled => date(task.scheduled, task.scheduled_time),
+            Column::ScheduledTime => time(task.scheduled_time),
+            Column::Start => date(task.start, task.start_time),
+            Column::StartTime => time(task.start_time),
+            Column::Done => date(task.done, task.done_time),
+            Column::DoneTime => time(task.done_time),
+            Column::Overdue => task.overdue.to_string(),
+            Column::NotStarted => task.not_started.to_string(),
+            Column::DueToday => task.due_today.to_string(),
+            Column::DueSoon => task.due_soon.to_string(),
+            Column::Priority => task.priority.as_str().to_string(),
+            Column::Recurrence => task.recurrence.clone().unwrap_or_default(),
+            Column::Estimate => task.estimate.map(format_duration).unwrap_or_default(),
+            Column::Urgency => format!("{:.2}", task.urgency),
+            Column::Tags => task.tags.join(" "),
+            Column::Contexts => task.contexts.join(" "),
+            Column::Id => task.id.clone().unwrap_or_default(),
+            Column::DependsOn => task.depends_on.join(","),
+            Column::Blocked => task.blocked.to_string(),
+            Column::BlockId => task.block_id.clone().unwrap_or_default(),
+            Column::Uid => task.uid.clone().unwrap_or_default(),
+            Column::Links => task.link_targets(),
+            Column::Notes => task.notes.clone().unwrap_or_default(),
+            Column::File => task.file.clone().unwrap_or_default(),
+            Column::Line => task.line.map(|l| l.to_string()).unwrap_or_default(),
+            Column::Parent => task.parent.map(|l| l.to_string()).unwrap_or_default(),
+        }
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Column {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "name" => Ok(Column::Name),
+            "completed" | "status" => Ok(Column::Completed),
+            "created" => Ok(Column::Created),
+            "due" => Ok(Column::Due),
+            "due_time" => Ok(Column::DueTime),
+            "scheduled" => Ok(Column::Scheduled),
+            "scheduled_time" => Ok(Column::ScheduledTime),
+            "start" => Ok(Column::Start),
+            "start_time" => Ok(Column::StartTime),
+            "done" => Ok(Column::Done),
+            "done_time" => Ok(Column::DoneTime),
+            "overdue" => Ok(Column::Overdue),
+            "not_started" => Ok(Column::NotStarted),
+            "due_today" => Ok(Column::DueToday),
+            "due_soon" => Ok(Column::DueSoon),
+            "priority" => Ok(Column::Priority),
+            "recurrence" => Ok(Column::Recurrence),
+            "estimate" => Ok(Column::Estimate),
+            "urgency" => Ok(Column::Urgency),
+            "tags" => Ok(Column::Tags),
+            "contexts" => Ok(Column::Contexts),
+            "id" => Ok(Column::Id),
+            "depends_on" => Ok(Column::DependsOn),
+            "blocked" => Ok(Column::Blocked),
+            "block_id" => Ok(Column::BlockId),
+            "uid" => Ok(Column::Uid),
+            "links" => Ok(Column::Links),
+            "notes" => Ok(Column::Notes),
+            "file" => Ok(Column::File),
+            "line" => Ok(Column::Line),
+            "parent" => Ok(Column::Parent),
+            other => Err(Error::InvalidColumn(other.to_string())),
+        }
+    }
+}
+
+/// Parses a comma-separated column list such as `name,due,priority`.
+pub fn parse_columns(spec: &str) -> Result<Vec<Column>> {
+    spec.split(',').map(str::parse).collect()
+}
+
+/// Field separator for delimited output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    Comma,
+    Tab,
+}
+
+/// Writes a header row followed by one row per task.
+pub fn write_tasks<W: Write>(
+    mut writer: W,
+    tasks: &[&Task],
+    columns: &[Column],
+    delimiter: Delimiter,
+) -> Result<()> {
+    let header: Vec<String> = columns.iter().map(|c| c.name().to_string()).collect();
+    write_row(&mut writer, &header, delimiter)?;
+    for task in tasks {
+        let row: Vec<String> = columns.iter().map(|c| c.value(task)).collect();
+        write_row(&mut writer, &row, delimiter)?;
+    }
+    Ok(())
+}
+
+fn write_row<W: Write>(writer: &mut W, fields: &[String], delimiter: Delimiter) -> Result<()> {
+    let (separator, terminator) = match delimiter {
+        Delimiter::Comma => (",", "\r\n"),
+        Delimiter::Tab => ("\t", "\n"),
+    };
+    let escaped: Vec<String> = fields
+        .iter()
+        .map(|field| match delimiter {
+            Delimiter::Comma => quote_csv(field),
+            Delimiter::Tab => field.replace(['\t', '\r', '\n'], " "),
+        })
+        .collect();
+    write!(writer, "{}{}", escaped.join(separator), terminator)?;
+    Ok(())
+}
+
+/// Quotes a CSV field per RFC 4180 when it contains a comma, a quote or a line break.
+fn quote_csv(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
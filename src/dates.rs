@@ -1,45 +1,109 @@
-use chrono::{Duration, Local, NaiveDate};
+use crate::error::{Error, Result};
+use chrono::{Duration, Local, NaiveDate, NaiveTime, TimeZone, Utc};
 use regex::Regex;
 
-/// Parses a date argument which could be an absolute date (YYYY-MM-DD) or
+/// Parses a date argument which could be an absolute date (YYYY-MM-DD), `today`, or
 /// a relative date (e.g., +1w, -2d), returning a NaiveDate.
-pub fn parse_date_arg(arg: Option<&str>) -> Option<NaiveDate> {
-    arg.and_then(|s| {
+///
+/// A missing argument yields `Ok(None)`; an unrecognised one is an error rather
+/// than being treated as "no date".
+pub fn parse_date_arg(arg: Option<&str>) -> Result<Option<NaiveDate>> {
+    arg.map(|s| {
         if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
             // Absolute date
-            Some(date)
-        } else if let Some(rel_date) = parse_relative_date(s) {
-            // Relative date
-            Some(rel_date)
+            Ok(date)
+        } else if s == "today" {
+            Ok(Local::now().date_naive())
         } else {
-            None
+            // Relative date
+            parse_relative_date(s)
         }
     })
+    .transpose()
 }
 
 /// Parses a relative date specification (e.g., "+1w", "-3d") and returns the corresponding NaiveDate.
-pub fn parse_relative_date(spec: &str) -> Option<NaiveDate> {
-    let today = Local::today().naive_local();
-    let re = Regex::new(r"([+-])(\d+)([dwmy])").unwrap();
-    re.captures(spec).and_then(|caps| {
-        let sign = caps.get(1)?.as_str();
-        let quantity: i64 = caps.get(2)?.as_str().parse().ok()?;
-        let unit = caps.get(3)?.as_str();
-
-        let duration = match unit {
-            "d" => Duration::days(quantity),
-            "w" => Duration::weeks(quantity),
-            "m" => Duration::days(quantity * 30), // Approximation
-            "y" => Duration::days(quantity * 365), // Approximation
-            _ => return None,
-        };
-
-        match sign {
-            "+" => Some(today + duration),
-            "-" => Some(today - duration),
-            _ => None,
-        }
-    })
+pub fn parse_relative_date(spec: &str) -> Result<NaiveDate> {
+    let today = Local::now().date_naive();
+    let re = Regex::new(r"^([+-])(\d+)([dwmy])$").unwrap();
+    let invalid = || Error::InvalidDate(spec.to_string());
+    let caps = re.captures(spec).ok_or_else(invalid)?;
+    let quantity: i64 = caps[2].parse().map_err(|_| invalid())?;
+
+    let days_per_unit = match &caps[3] {
+        "d" => 1,
+        "w" => 7,
+        "m" => 30, // Approximation
+        _ => 365,  // "y", approximation
+    };
+    // Offsets beyond the range of dates are invalid rather than a panic
+    let duration = quantity
+        .checked_mul(days_per_unit)
+        .and_then(Duration::try_days)
+        .ok_or_else(invalid)?;
+
+    match &caps[1] {
+        "+" => today.checked_add_signed(duration),
+        _ => today.checked_sub_signed(duration),
+    }
+    .ok_or_else(invalid)
+}
+
+/// Describes `date` relative to `today`, e.g. "today", "in 3 days" or "2 days ago".
+pub fn describe_relative(date: NaiveDate, today: NaiveDate) -> String {
+    match (date - today).num_days() {
+        0 => "today".to_string(),
+        1 => "tomorrow".to_string(),
+        -1 => "yesterday".to_string(),
+        days if days > 0 => format!("in {} days", days),
+        days => format!("{} days ago", -days),
+    }
+}
+
+/// Describes a due date relative to `today`, phrasing past dates as overdue,
+/// e.g. "in 3 days" or "2 days overdue".
+pub fn describe_due(date: NaiveDate, today: NaiveDate) -> String {
+    match (today - date).num_days() {
+        1 => "1 day overdue".to_string(),
+        days if days > 1 => format!("{} days overdue", days),
+        _ => describe_relative(date, today),
+    }
+}
+
+/// Parses an estimate such as `45m`, `2h` or `1h30m` into minutes.
+pub fn parse_duration(text: &str) -> Option<u32> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let re = Regex::new(r"^(?:(\d+)h)?(?:(\d+)m)?$").unwrap();
+    let caps = re.captures(text)?;
+    let hours: u32 = caps.get(1).map_or(Some(0), |m| m.as_str().parse().ok())?;
+    let minutes: u32 = caps.get(2).map_or(Some(0), |m| m.as_str().parse().ok())?;
+    hours.checked_mul(60)?.checked_add(minutes)
+}
+
+/// Formats minutes as an estimate such as `45m`, `2h` or `1h30m`.
+pub fn format_duration(minutes: u32) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{}m", minutes),
+        (hours, 0) => format!("{}h", hours),
+        (hours, minutes) => format!("{}h{}m", hours, minutes),
+    }
+}
+
+/// The local `time` on `date`, or midnight when there is none, as a UTC timestamp such
+/// as `20240105T230000Z`, the form Taskwarrior and iCalendar use.
+pub(crate) fn utc_timestamp(date: NaiveDate, time: Option<NaiveTime>) -> String {
+    let local_time = date.and_time(time.unwrap_or(NaiveTime::MIN));
+    let local = Local
+        .from_local_datetime(&local_time)
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&local_time));
+    local
+        .with_timezone(&Utc)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
 }
 
 #[cfg(test)]
@@ -51,42 +115,97 @@ mod tests {
     fn parse_absolute_date() {
         // Test with a valid absolute date
         let date_str = "2024-01-01";
-        let expected_date = NaiveDate::from_ymd(2024, 1, 1);
-        assert_eq!(parse_date_arg(Some(date_str)), Some(expected_date));
+        let expected_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        assert_eq!(parse_date_arg(Some(date_str)).unwrap(), Some(expected_date));
     }
 
     #[test]
     fn parse_relative_date_weeks() {
         // Assuming today's date for testing purposes
-        let today = Local::today().naive_local();
+        let today = Local::now().date_naive();
         let expected_date = today + Duration::weeks(1);
 
         // Test with a valid relative date of "+1w" (1 week from today)
-        assert_eq!(parse_date_arg(Some("+1w")), Some(expected_date));
+        assert_eq!(parse_date_arg(Some("+1w")).unwrap(), Some(expected_date));
     }
 
     #[test]
     fn parse_relative_date_days() {
         // Assuming today's date for testing purposes
-        let today = Local::today().naive_local();
+        let today = Local::now().date_naive();
         let expected_date = today - Duration::days(2);
 
         // Test with a valid relative date of "-2d" (2 days before today)
-        assert_eq!(parse_date_arg(Some("-2d")), Some(expected_date));
+        assert_eq!(parse_date_arg(Some("-2d")).unwrap(), Some(expected_date));
     }
 
     #[test]
     fn parse_invalid_date() {
         // Test with an invalid date string
-        assert_eq!(parse_date_arg(Some("invalid-date")), None);
+        assert!(parse_date_arg(Some("invalid-date")).is_err());
 
         // Test with an invalid relative date format
-        assert_eq!(parse_date_arg(Some("+1x")), None); // "x" is not a recognized duration unit
+        assert!(parse_date_arg(Some("+1x")).is_err()); // "x" is not a recognized duration unit
+
+        // Test with trailing garbage after a relative date
+        assert!(parse_date_arg(Some("+1wk")).is_err());
+    }
+
+    #[test]
+    fn parse_overflowing_offset() {
+        // Offsets past the range of dates are invalid dates, not a panic
+        for spec in ["+99999999999d", "-99999999999d", "+9999999999999999y"] {
+            assert!(matches!(
+                parse_date_arg(Some(spec)),
+                Err(Error::InvalidDate(s)) if s == spec
+            ));
+        }
+    }
+
+    #[test]
+    fn describe_dates() {
+        let today = NaiveDate::from_ymd_opt(2024, 2, 10).unwrap();
+
+        assert_eq!(describe_relative(today, today), "today");
+        assert_eq!(
+            describe_relative(today + Duration::days(1), today),
+            "tomorrow"
+        );
+        assert_eq!(
+            describe_relative(today + Duration::days(3), today),
+            "in 3 days"
+        );
+        assert_eq!(
+            describe_relative(today - Duration::days(2), today),
+            "2 days ago"
+        );
+        assert_eq!(
+            describe_due(today - Duration::days(1), today),
+            "1 day overdue"
+        );
+        assert_eq!(
+            describe_due(today - Duration::days(2), today),
+            "2 days overdue"
+        );
+        assert_eq!(describe_due(today + Duration::days(3), today), "in 3 days");
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("45m"), Some(45));
+        assert_eq!(parse_duration("2h"), Some(120));
+        assert_eq!(parse_duration("1h30m"), Some(90));
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("soon"), None);
+
+        assert_eq!(format_duration(45), "45m");
+        assert_eq!(format_duration(120), "2h");
+        assert_eq!(format_duration(90), "1h30m");
     }
 
     #[test]
     fn parse_none() {
         // Test with None as input
-        assert_eq!(parse_date_arg(None), None);
+        assert_eq!(parse_date_arg(None).unwrap(), None);
     }
 }
@@ -0,0 +1,179 @@
+//! Dependencies between tasks, declared with `🆔 abc123` and `⛔ abc123,def456`.
+//!
+//! IDs are resolved across every task read in one run, whichever file they came from.
+//! A task is blocked while any task it depends on is still open; references to IDs no
+//! task declares do not block, but [`check`] reports them, along with duplicate IDs
+//! and cycles.
+
+use crate::error::Result;
+use crate::Task;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+
+/// A problem with the dependencies of the task at index `task`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub task: usize,
+    pub message: String,
+}
+
+/// IDs of the tasks that are not completed, which block the tasks depending on them.
+pub fn open_ids(tasks: &[&Task]) -> HashSet<String> {
+    tasks
+        .iter()
+        .filter(|task| !task.completed)
+        .filter_map(|task| task.id.clone())
+        .collect()
+}
+
+/// Whether `task` depends on a task whose ID is in `open_ids`.
+pub fn is_blocked(task: &Task, open_ids: &HashSet<String>) -> bool {
+    task.depends_on.iter().any(|id| open_ids.contains(id))
+}
+
+/// Reports duplicate IDs, references to unknown IDs and dependency cycles.
+pub fn check(tasks: &[&Task]) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut owners: HashMap<&str, usize> = HashMap::new();
+    for (index, task) in tasks.iter().enumerate() {
+        let Some(id) = task.id.as_deref() else {
+            continue;
+        };
+        match owners.get(id) {
+            Some(&first) => problems.push(Problem {
+                task: index,
+                message: format!(
+                    "duplicate task id `{}`, first used {}",
+                    id,
+                    location(tasks[first])
+                ),
+            }),
+            None => {
+                owners.insert(id, index);
+            }
+        }
+    }
+
+    for (index, task) in tasks.iter().enumerate() {
+        for id in &task.depends_on {
+            if !owners.contains_key(id.as_str()) {
+                problems.push(Problem {
+                    task: index,
+                    message: format!("depends on unknown task id `{}`", id),
+                });
+            }
+        }
+    }
+
+    // Depth-first search over the IDs, reporting each cycle once on its first task
+    let mut finished: HashSet<&str> = HashSet::new();
+    for (index, task) in tasks.iter().enumerate() {
+        let Some(id) = task.id.as_deref() else {
+            continue;
+        };
+        if owners.get(id) != Some(&index) || finished.contains(id) {
+            continue;
+        }
+        visit(id, tasks, &owners, &mut finished, &mut problems);
+    }
+    problems.sort_by_key(|problem| problem.task);
+    problems
+}
+
+/// Follows the dependencies of the task declaring `start`, depth first, reporting the
+/// cycles found. The path is kept on an explicit stack rather than the call stack, so
+/// long chains of dependencies cannot overflow it.
+fn visit<'a>(
+    start: &'a str,
+    tasks: &[&'a Task],
+    owners: &HashMap<&'a str, usize>,
+    finished: &mut HashSet<&'a str>,
+    problems: &mut Vec<Problem>,
+) {
+    // Each ID on the path, with the position of the next dependency to follow from it
+    let mut path: Vec<(&'a str, usize)> = vec![(start, 0)];
+    let mut on_path: HashMap<&'a str, usize> = HashMap::from([(start, 0)]);
+    while let Some(&(id, next)) = path.last() {
+        let task: &'a Task = tasks[owners[id]];
+        let Some(dependency) = task.depends_on.get(next) else {
+            path.pop();
+            on_path.remove(id);
+            finished.insert(id);
+            continue;
+        };
+        let top = path.len() - 1;
+        path[top].1 += 1;
+
+        let dependency = dependency.as_str();
+        if let Some(&position) = on_path.get(dependency) {
+            let mut cycle: Vec<&str> = path[position..].iter().map(|&(id, _)| id).collect();
+            cycle.push(dependency);
+            problems.push(Problem {
+                task: owners[dependency],
+                message: format!("dependency cycle {}", cycle.join(" ⛔ ")),
+            });
+        } else if owners.contains_key(dependency) && !finished.contains(dependency) {
+            on_path.insert(dependency, path.len());
+            path.push((dependency, 0));
+        }
+    }
+}
+
+/// Writes the dependencies between `tasks` as a Graphviz DOT digraph, with an edge
+/// from each task to the tasks waiting on it. Completed tasks are drawn dashed, and IDs
+/// no task declares as dotted boxes.
+pub fn write_dot<W: Write>(mut writer: W, tasks: &[&Task]) -> Result<()> {
+    let nodes: HashMap<&str, usize> = tasks
+        .iter()
+        .enumerate()
+        .filter_map(|(index, task)| Some((task.id.as_deref()?, index)))
+        .collect();
+    let node = |id: &str| match nodes.get(id) {
+        Some(index) => format!("t{}", index),
+        None => quote(id),
+    };
+
+    writeln!(writer, "digraph tasks {{")?;
+    writeln!(writer, "  node [shape=box];")?;
+    let mut missing: Vec<&str> = Vec::new();
+    for (index, task) in tasks.iter().enumerate() {
+        if task.id.is_none() && task.depends_on.is_empty() {
+            continue;
+        }
+        let style = if task.completed { ", style=dashed" } else { "" };
+        writeln!(
+            writer,
+            "  t{} [label={}{}];",
+            index,
+            quote(&task.name),
+            style
+        )?;
+        for id in &task.depends_on {
+            if !nodes.contains_key(id.as_str()) && !missing.contains(&id.as_str()) {
+                missing.push(id);
+            }
+        }
+    }
+    for id in missing {
+        writeln!(writer, "  {} [style=dotted];", quote(id))?;
+    }
+    for (index, task) in tasks.iter().enumerate() {
+        for id in &task.depends_on {
+            writeln!(writer, "  {} -> t{};", node(id), index)?;
+        }
+    }
+    writeln!(writer, "}}")?;
+    Ok(())
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn location(task: &Task) -> String {
+    match (&task.file, task.line) {
+        (Some(file), Some(line)) => format!("at {}:{}", file, line),
+        (None, Some(line)) => format!("on line {}", line),
+        _ => "elsewhere".to_string(),
+    }
+}
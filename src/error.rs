@@ -0,0 +1,102 @@
+use crate::Diagnostic;
+use std::fmt;
+use std::io;
+
+/// Errors returned by the fallible parts of the `marktask` API.
+#[derive(Debug)]
+pub enum Error {
+    /// A date argument was neither `YYYY-MM-DD` nor a relative date such as `+1w`.
+    InvalidDate(String),
+    /// An output column name was not recognised.
+    InvalidColumn(String),
+    /// An output template could not be parsed.
+    Template(String),
+    /// The configuration file could not be understood.
+    Config(String),
+    /// Reading input or writing output failed.
+    Io(io::Error),
+    /// Tasks could not be serialized as JSON.
+    Json(serde_json::Error),
+    /// JSON input was malformed or did not describe tasks.
+    InvalidJson(serde_json::Error),
+    /// JSON input was written for a schema version this build does not understand.
+    UnsupportedVersion(u64),
+    /// The input contained malformed tasks.
+    Parse(Vec<Diagnostic>),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidDate(value) => write!(
+                f,
+                "invalid date `{}`: expected YYYY-MM-DD or a relative date such as +1w or -2d",
+                value
+            ),
+            Error::InvalidColumn(name) => write!(
+                f,
+                "unknown column `{}`: expected one of {}",
+                name,
+                crate::csv::Column::ALL
+                    .map(|column| column.name())
+                    .join(", ")
+            ),
+            Error::Template(message) => write!(f, "invalid template: {}", message),
+            Error::Config(message) => write!(f, "invalid configuration: {}", message),
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::Json(err) => write!(f, "JSON error: {}", err),
+            Error::InvalidJson(err) => write!(f, "invalid JSON input: {}", err),
+            Error::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported JSON schema version {} (expected {})",
+                version,
+                crate::json::SCHEMA_VERSION
+            ),
+            Error::Parse(diagnostics) => match diagnostics.as_slice() {
+                [diagnostic] => write!(f, "found 1 problem in input ({})", diagnostic),
+                _ => write!(f, "found {} problems in input", diagnostics.len()),
+            },
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Json(err) | Error::InvalidJson(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl Error {
+    /// Wraps an error from reading JSON input, keeping I/O failures apart from
+    /// malformed input.
+    pub(crate) fn json_input(err: serde_json::Error) -> Self {
+        if err.is_io() {
+            Error::Io(err.into())
+        } else {
+            Error::InvalidJson(err)
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    /// Failures writing the output are I/O errors, so a closed pipe is not reported.
+    fn from(err: serde_json::Error) -> Self {
+        if err.is_io() {
+            Error::Io(err.into())
+        } else {
+            Error::Json(err)
+        }
+    }
+}
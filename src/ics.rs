@@ -0,0 +1,390 @@
+//! iCalendar (RFC 5545) export and import, so calendar apps can subscribe to task
+//! deadlines and tasks can be migrated out of them.
+//!
+//! Each task becomes a `VTODO` with `DUE` from 📅, `DTSTART` from 🛫, `PRIORITY`
+//! mapped from [`Priority`], `STATUS` from completion and `RRULE` from a 🔁 rule
+//! that can be expressed in iCalendar terms. Scheduled dates (⏳) have no standard
+//! property and are kept in `X-MARKTASK-SCHEDULED`. Dates with a time of day are
+//! written as floating local times, and the others as whole days, except that `DTSTART`
+//! and `DUE` are both times when either has one. Done dates (✅) become `COMPLETED`
+//! timestamps. Its `UID` is the task's `uid`, so it stays the same across exports.
+
+use crate::dates::utc_timestamp;
+use crate::error::{Error, Result};
+use crate::recurrence::{Interval, Unit};
+use crate::{Diagnostic, ImportedTask, Priority, Task};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use std::io::{Read, Write};
+
+const PRODID: &str = concat!("-//marktask//marktask ", env!("CARGO_PKG_VERSION"), "//EN");
+/// Content lines longer than this many octets are folded.
+const MAX_LINE_OCTETS: usize = 75;
+
+const WEEKDAYS: [(&str, &str); 7] = [
+    ("monday", "MO"),
+    ("tuesday", "TU"),
+    ("wednesday", "WE"),
+    ("thursday", "TH"),
+    ("friday", "FR"),
+    ("saturday", "SA"),
+    ("sunday", "SU"),
+];
+
+/// Writes `tasks` as a calendar with one `VTODO` each, stamped with `now`.
+pub fn write_calendar<W: Write>(mut writer: W, tasks: &[&Task], now: DateTime<Utc>) -> Result<()> {
+    let stamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+    write_line(&mut writer, "BEGIN:VCALENDAR")?;
+    write_line(&mut writer, "VERSION:2.0")?;
+    write_line(&mut writer, &format!("PRODID:{}", PRODID))?;
+    for task in tasks {
+        write_line(&mut writer, "BEGIN:VTODO")?;
+        write_line(&mut writer, &format!("UID:{}", uid(task)))?;
+        write_line(&mut writer, &format!("DTSTAMP:{}", stamp))?;
+        write_line(&mut writer, &format!("SUMMARY:{}", escape_text(&task.name)))?;
+        // DTSTART and DUE must have the same value type, so a time on either makes both
+        // date-times
+        let timed = task.start_time.is_some() || task.due_time.is_some();
+        let dates = [
+            ("DTSTART", task.start, task.start_time, timed),
+            ("DUE", task.due, task.due_time, timed),
+            (
+                "X-MARKTASK-SCHEDULED",
+                task.scheduled,
+                task.scheduled_time,
+                task.scheduled_time.is_some(),
+            ),
+        ];
+        for (property, date, time, timed) in dates {
+            if let Some(date) = date {
+                write_line(&mut writer, &date_property(property, date, time, timed))?;
+            }
+        }
+        if let Some(priority) = ics_priority(task.priority) {
+            write_line(&mut writer, &format!("PRIORITY:{}", priority))?;
+        }
+        let status = if task.completed {
+            "COMPLETED"
+        } else {
+            "NEEDS-ACTION"
+        };
+        write_line(&mut writer, &format!("STATUS:{}", status))?;
+        if let Some(done) = task.done.filter(|_| task.completed) {
+            let completed = utc_timestamp(done, task.done_time);
+            write_line(&mut writer, &format!("COMPLETED:{}", completed))?;
+        }
+        if let Some(rule) = task.recurrence.as_deref().and_then(rrule) {
+            write_line(&mut writer, &format!("RRULE:{}", rule))?;
+        }
+        write_line(&mut writer, "END:VTODO")?;
+    }
+    write_line(&mut writer, "END:VCALENDAR")?;
+    Ok(())
+}
+
+/// A stable identifier for `task`: its `uid`, or [`Task::stable_id`] when it has none.
+pub fn uid(task: &Task) -> String {
+    format!(
+        "{}@marktask",
+        task.uid.clone().unwrap_or_else(|| task.stable_id())
+    )
+}
+
+/// Reads every `VTODO` in an iCalendar stream.
+///
+/// Dates that cannot be read are reported together as an [`Error::Parse`], located
+/// by the physical line their property starts on.
+pub fn read_todos<R: Read>(mut reader: R) -> Result<Vec<ImportedTask>> {
+    let mut input = String::new();
+    reader.read_to_string(&mut input)?;
+
+    let mut todos = Vec::new();
+    let mut diagnostics = Vec::new();
+    let mut current: Option<ImportedTask> = None;
+    let today = Local::now().date_naive();
+
+    for (line_number, line) in unfold(&input) {
+        let Some((name, value)) = split_property(&line) else {
+            continue;
+        };
+        match (name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if value.eq_ignore_ascii_case("VTODO") => {
+                current = Some(ImportedTask::default());
+            }
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VTODO") => {
+                let mut todo = current.take().expect("inside a VTODO");
+                todo.task.overdue =
+                    !todo.task.completed && todo.task.due.is_some_and(|d| d < today);
+                todos.push(todo);
+            }
+            (_, Some(todo)) => {
+                let mut date = |field: &str| {
+                    let parsed = parse_date_time(&value);
+                    if parsed.is_none() {
+                        diagnostics.push(Diagnostic {
+                            line: line_number,
+                            column: 1,
+                            message: format!("invalid {} date `{}`", field, value),
+                        });
+                    }
+                    (
+                        parsed.map(|(date, _)| date),
+                        parsed.and_then(|(_, time)| time),
+                    )
+                };
+                let task = &mut todo.task;
+                match name.as_str() {
+                    "UID" => todo.uid = Some(value),
+                    "SUMMARY" => task.name = unescape_text(&value),
+                    "DUE" => (task.due, task.due_time) = date("due"),
+                    "DTSTART" => (task.start, task.start_time) = date("start"),
+                    "X-MARKTASK-SCHEDULED" => {
+                        (task.scheduled, task.scheduled_time) = date("scheduled")
+                    }
+                    "PRIORITY" => {
+                        task.priority = value
+                            .parse()
+                            .map(priority_from_ics)
+                            .unwrap_or(Priority::None)
+                    }
+                    "STATUS" => task.completed = value.eq_ignore_ascii_case("COMPLETED"),
+                    "COMPLETED" => {
+                        task.completed = true;
+                        // Completion times are in UTC, and local midnight stands for a
+                        // whole day
+                        let (done, time) = date("completion");
+                        task.done = done;
+                        task.done_time = time.filter(|&time| time != NaiveTime::MIN);
+                    }
+                    "RRULE" => task.recurrence = recurrence_from_rrule(&value),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if diagnostics.is_empty() {
+        Ok(todos)
+    } else {
+        Err(Error::Parse(diagnostics))
+    }
+}
+
+/// Converts an Obsidian Tasks recurrence rule such as "every 2 weeks" or
+/// "every week on Monday, Friday" into an iCalendar `RRULE` value, when possible.
+pub fn rrule(recurrence: &str) -> Option<String> {
+    if let Some(interval) = Interval::parse(recurrence) {
+        let freq = match interval.unit {
+            Unit::Day => "DAILY",
+            Unit::Week => "WEEKLY",
+            Unit::Month => "MONTHLY",
+            Unit::Year => "YEARLY",
+        };
+        return match interval.count {
+            1 => Some(format!("FREQ={}", freq)),
+            count => Some(format!("FREQ={};INTERVAL={}", freq, count)),
+        };
+    }
+    let rule = recurrence.trim().to_lowercase();
+    let rule = rule.strip_suffix("when done").unwrap_or(&rule).trim();
+    let rest = rule.strip_prefix("every")?.trim();
+    let rest = rest.strip_prefix("week on").unwrap_or(rest).trim();
+
+    if rest == "weekday" {
+        return Some("FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR".to_string());
+    }
+
+    let days: Option<Vec<&str>> = rest
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|word| !word.is_empty() && *word != "and")
+        .map(|word| {
+            WEEKDAYS
+                .iter()
+                .find(|(name, _)| *name == word)
+                .map(|(_, code)| *code)
+        })
+        .collect();
+    match days {
+        Some(days) if !days.is_empty() => Some(format!("FREQ=WEEKLY;BYDAY={}", days.join(","))),
+        _ => None,
+    }
+}
+
+/// Converts an `RRULE` value back into an Obsidian Tasks recurrence rule. Parts other
+/// than the frequency, interval and weekdays are dropped.
+pub fn recurrence_from_rrule(rule: &str) -> Option<String> {
+    let mut freq = None;
+    let mut interval = 1;
+    let mut days = Vec::new();
+    for part in rule.split(';') {
+        match part.split_once('=')? {
+            ("FREQ", value) => freq = Some(value),
+            ("INTERVAL", value) => interval = value.parse().ok()?,
+            ("BYDAY", value) => days = value.split(',').collect(),
+            _ => {}
+        }
+    }
+    let unit = match freq? {
+        "DAILY" => Unit::Day,
+        "WEEKLY" => Unit::Week,
+        "MONTHLY" => Unit::Month,
+        "YEARLY" => Unit::Year,
+        _ => return None,
+    };
+
+    if unit == Unit::Week && interval == 1 && !days.is_empty() {
+        if days == ["MO", "TU", "WE", "TH", "FR"] {
+            return Some("every weekday".to_string());
+        }
+        let names: Option<Vec<String>> = days
+            .iter()
+            .map(|code| {
+                WEEKDAYS
+                    .iter()
+                    .find(|(_, c)| c == code)
+                    .map(|(name, _)| capitalise(name))
+            })
+            .collect();
+        return names.map(|names| format!("every week on {}", names.join(", ")));
+    }
+    Some(
+        Interval {
+            count: interval,
+            unit,
+            when_done: false,
+        }
+        .to_string(),
+    )
+}
+
+fn capitalise(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Maps iCalendar's 1 (highest) to 9 (lowest) scale onto a priority; 0 means undefined.
+fn priority_from_ics(priority: u8) -> Priority {
+    match priority {
+        1 => Priority::Highest,
+        2..=4 => Priority::High,
+        5 => Priority::Medium,
+        6..=8 => Priority::Low,
+        9 => Priority::Lowest,
+        _ => Priority::None,
+    }
+}
+
+/// Maps a priority onto iCalendar's 1 (highest) to 9 (lowest) scale; `None` is undefined.
+fn ics_priority(priority: Priority) -> Option<u8> {
+    match priority {
+        Priority::Highest => Some(1),
+        Priority::High => Some(3),
+        Priority::Medium => Some(5),
+        Priority::Low => Some(7),
+        Priority::Lowest => Some(9),
+        Priority::None => None,
+    }
+}
+
+/// A date property, as a floating local `DATE-TIME` when `timed`, at midnight if the
+/// task gives no time of day, and as a `DATE` otherwise.
+fn date_property(property: &str, date: NaiveDate, time: Option<NaiveTime>, timed: bool) -> String {
+    if timed {
+        let date_time = date.and_time(time.unwrap_or(NaiveTime::MIN));
+        format!("{}:{}", property, date_time.format("%Y%m%dT%H%M%S"))
+    } else {
+        format!("{};VALUE=DATE:{}", property, date.format("%Y%m%d"))
+    }
+}
+
+/// Reads a `DATE` or `DATE-TIME` value. UTC times (ending in `Z`) are converted to local
+/// time, and floating or zoned times are taken as they are written.
+fn parse_date_time(value: &str) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    if !value.contains('T') {
+        let date = NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()?;
+        return Some((date, None));
+    }
+    let written = NaiveDateTime::parse_from_str(value.get(..15)?, "%Y%m%dT%H%M%S").ok()?;
+    let local = if value.ends_with('Z') {
+        Utc.from_utc_datetime(&written)
+            .with_timezone(&Local)
+            .naive_local()
+    } else {
+        written
+    };
+    Some((local.date(), Some(local.time())))
+}
+
+/// Escapes a TEXT value (RFC 5545 section 3.3.11).
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Reverses [`escape_text`]. Line breaks become spaces, as task names are single lines.
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, c == '\\') {
+            (_, true) => match chars.next() {
+                Some('n') | Some('N') => unescaped.push(' '),
+                Some(escaped) => unescaped.push(escaped),
+                None => {}
+            },
+            (c, false) => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Joins folded lines, yielding each logical line with the physical line it starts on.
+fn unfold(input: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some((_, previous))) => previous.push_str(continuation),
+            _ => lines.push((index + 1, line.to_string())),
+        }
+    }
+    lines
+}
+
+/// Splits a content line into its upper-cased property name (without parameters) and value.
+fn split_property(line: &str) -> Option<(String, String)> {
+    let mut in_quotes = false;
+    let colon = line.char_indices().find_map(|(index, c)| match c {
+        '"' => {
+            in_quotes = !in_quotes;
+            None
+        }
+        ':' if !in_quotes => Some(index),
+        _ => None,
+    })?;
+    let name = line[..colon].split(';').next().unwrap_or("");
+    Some((name.to_uppercase(), line[colon + 1..].to_string()))
+}
+
+/// Writes a content line terminated by CRLF, folding it every 75 octets without
+/// splitting a UTF-8 sequence.
+fn write_line<W: Write>(writer: &mut W, line: &str) -> Result<()> {
+    let mut start = 0;
+    let mut limit = MAX_LINE_OCTETS;
+    while line.len() - start > limit {
+        let mut end = start + limit;
+        while !line.is_char_boundary(end) {
+            end -= 1;
+        }
+        write!(writer, "{}\r\n ", &line[start..end])?;
+        start = end;
+        // Continuation lines start with a space, which counts towards the limit
+        limit = MAX_LINE_OCTETS - 1;
+    }
+    write!(writer, "{}\r\n", &line[start..])?;
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! Reading and writing tasks as JSON, the format produced by `marktask --json`.
+//!
+//! Tasks are wrapped in a versioned envelope:
+//!
+//! ```json
+//! {"version": 1, "generated_at": "2024-02-01T09:30:00Z", "tasks": [...]}
+//! ```
+//!
+//! Every task field is always present, with `null` standing in for missing dates.
+//! For streaming, [`write_task_line`] writes newline-delimited JSON instead: one bare
+//! task object per line, with no envelope.
+//! The layout for each version is described by the JSON Schema returned by [`schema`].
+
+use crate::error::{Error, Result};
+use crate::Task;
+use chrono::{Local, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+/// The envelope version written by this build.
+pub const SCHEMA_VERSION: u64 = 1;
+
+const SCHEMA_V1: &str = include_str!("../schema/marktask-v1.schema.json");
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    version: u64,
+    generated_at: String,
+    tasks: &'a [&'a Task],
+}
+
+#[derive(Deserialize)]
+struct RawEnvelope {
+    version: u64,
+    tasks: serde_json::Value,
+}
+
+/// Returns the JSON Schema describing envelope version `version`, if it is known.
+pub fn schema(version: u64) -> Option<&'static str> {
+    match version {
+        1 => Some(SCHEMA_V1),
+        _ => None,
+    }
+}
+
+/// Reads a task envelope, as previously written by [`write_tasks`].
+pub fn read_tasks<R: Read>(reader: R) -> Result<Vec<Task>> {
+    let envelope: RawEnvelope = serde_json::from_reader(reader).map_err(Error::json_input)?;
+    if envelope.version != SCHEMA_VERSION {
+        return Err(Error::UnsupportedVersion(envelope.version));
+    }
+    let mut tasks: Vec<Task> = serde_json::from_value(envelope.tasks).map_err(Error::json_input)?;
+    // Whether a task is overdue depends on when it is read, not when it was written
+    let now = Local::now().naive_local();
+    for task in &mut tasks {
+        task.overdue = task.due.is_some_and(|due| match task.due_time {
+            Some(time) => due.and_time(time) < now,
+            None => due < now.date(),
+        });
+    }
+    Ok(tasks)
+}
+
+/// Writes `tasks` as a single-line envelope followed by a newline.
+pub fn write_tasks<W: Write>(mut writer: W, tasks: &[&Task]) -> Result<()> {
+    let envelope = Envelope {
+        version: SCHEMA_VERSION,
+        generated_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        tasks,
+    };
+    serde_json::to_writer(&mut writer, &envelope)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// Writes a single task as one line of newline-delimited JSON.
+pub fn write_task_line<W: Write>(mut writer: W, task: &Task) -> Result<()> {
+    serde_json::to_writer(&mut writer, task)?;
+    writeln!(writer)?;
+    Ok(())
+}
@@ -1,7 +1,32 @@
-use chrono::{Local, NaiveDate};
-use regex::Regex;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+pub mod color;
+pub mod config;
+pub mod csv;
 pub mod dates;
+pub mod dependencies;
+pub mod error;
+pub mod ics;
+pub mod json;
+pub mod links;
+pub mod logseq;
+pub mod markdown;
+pub mod mentions;
+pub mod org;
+pub mod parser;
+pub mod recurrence;
+pub mod table;
+pub mod taskwarrior;
+pub mod template;
+pub mod todotxt;
+pub mod urgency;
+
+pub use error::Error;
+pub use links::{Link, LinkKind};
+pub use parser::{
+    parse_input, parse_priority, parse_reader, Diagnostic, ParsedInput, Parser, TaskReader,
+};
 mod serializers {
     use chrono::NaiveDate;
     use serde::{Deserializer, Serializer};
@@ -23,7 +48,6 @@ mod serializers {
     where
         D: Deserializer<'de>,
     {
-        use chrono::format::ParseError;
         use serde::de::{self, Visitor};
 
         struct DateVisitor;
@@ -35,6 +59,20 @@ mod serializers {
                 formatter.write_str("a formatted date string")
             }
 
+            fn visit_none<E>(self) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(None)
+            }
+
+            fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                deserializer.deserialize_str(self)
+            }
+
             fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
             where
                 E: de::Error,
@@ -49,6 +87,29 @@ mod serializers {
     }
 }
 
+/// Times of day, written as `14:00`.
+mod time_serializers {
+    use chrono::NaiveTime;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S>(time: &Option<NaiveTime>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        time.map(|t| t.format("%H:%M").to_string())
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<NaiveTime>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|value| NaiveTime::parse_from_str(&value, "%H:%M").map_err(de::Error::custom))
+            .transpose()
+    }
+}
+
 pub trait Filter {
     fn apply<'a>(&self, tasks: Vec<&'a Task>) -> Vec<&'a Task>;
 }
@@ -74,25 +135,124 @@ pub struct DateRangeFilter {
 
 impl Filter for DateRangeFilter {
     fn apply<'a>(&self, tasks: Vec<&'a Task>) -> Vec<&'a Task> {
-        tasks.into_iter().filter(|&task| {
-            // Match against the combination of 'from' date, 'to' date, and the task's due date
-            match (&self.from_date, &self.to_date, &task.due) {
-                (Some(from), Some(to), Some(date)) => date >= from && date <= to,
-                (Some(from), None, Some(date)) => date >= from,
-                (None, Some(to), Some(date)) => date <= to,
-                (None, None, _) => true, // Include tasks when no date filter is applied
-                (_, _, None) => false, // Exclude tasks without due dates when any date filter is applied
-            }
-        }).collect()
+        tasks
+            .into_iter()
+            .filter(|&task| {
+                // Match against the combination of 'from' date, 'to' date, and the task's due date
+                match (&self.from_date, &self.to_date, &task.due) {
+                    (Some(from), Some(to), Some(date)) => date >= from && date <= to,
+                    (Some(from), None, Some(date)) => date >= from,
+                    (None, Some(to), Some(date)) => date <= to,
+                    (None, None, _) => true, // Include tasks when no date filter is applied
+                    (_, _, None) => false, // Exclude tasks without due dates when any date filter is applied
+                }
+            })
+            .collect()
     }
 }
 
+/// Hides tasks that have not started yet, whose start (🛫) or scheduled (⏳) date is
+/// after `today`.
+pub struct ActiveFilter {
+    pub today: NaiveDate,
+}
 
+impl Filter for ActiveFilter {
+    fn apply<'a>(&self, tasks: Vec<&'a Task>) -> Vec<&'a Task> {
+        tasks
+            .into_iter()
+            .filter(|task| {
+                let started = |date: Option<NaiveDate>| date.is_none_or(|date| date <= self.today);
+                started(task.start) && started(task.scheduled)
+            })
+            .collect()
+    }
+}
+
+/// Keeps tasks scheduled (⏳) on a given date.
+pub struct ScheduledFilter {
+    pub date: NaiveDate,
+}
+
+impl Filter for ScheduledFilter {
+    fn apply<'a>(&self, tasks: Vec<&'a Task>) -> Vec<&'a Task> {
+        tasks
+            .into_iter()
+            .filter(|task| task.scheduled == Some(self.date))
+            .collect()
+    }
+}
+
+/// Keeps tasks that link to or embed a note, matched by name regardless of folder,
+/// heading or case.
+pub struct LinkFilter {
+    pub note: String,
+}
+
+impl Filter for LinkFilter {
+    fn apply<'a>(&self, tasks: Vec<&'a Task>) -> Vec<&'a Task> {
+        let wanted = links::note_name(&self.note).to_lowercase();
+        tasks
+            .into_iter()
+            .filter(|task| {
+                task.links
+                    .iter()
+                    .filter_map(Link::note)
+                    .any(|note| note.to_lowercase() == wanted)
+            })
+            .collect()
+    }
+}
+
+/// Keeps tasks mentioning a person or context (`@alice`, `@phone`), ignoring case.
+pub struct ContextFilter {
+    pub context: String,
+}
+
+impl Filter for ContextFilter {
+    fn apply<'a>(&self, tasks: Vec<&'a Task>) -> Vec<&'a Task> {
+        let wanted = self.context.trim_start_matches('@');
+        tasks
+            .into_iter()
+            .filter(|task| {
+                task.contexts
+                    .iter()
+                    .any(|context| context.eq_ignore_ascii_case(wanted))
+            })
+            .collect()
+    }
+}
+
+/// Keeps the open tasks waiting on another open task, or, with `show_blocked` false,
+/// the open tasks that can be started now. `open_ids` holds the IDs of every open task
+/// read, so dependencies resolve across files.
+pub struct BlockedFilter {
+    pub open_ids: HashSet<String>,
+    pub show_blocked: bool,
+}
+
+impl Filter for BlockedFilter {
+    fn apply<'a>(&self, tasks: Vec<&'a Task>) -> Vec<&'a Task> {
+        tasks
+            .into_iter()
+            .filter(|task| {
+                !task.completed
+                    && dependencies::is_blocked(task, &self.open_ids) == self.show_blocked
+            })
+            .collect()
+    }
+}
 
 pub struct FilterPipeline {
     pub filters: Vec<Box<dyn Filter>>,
 }
 
+impl Default for FilterPipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl FilterPipeline {
     pub fn new() -> Self {
         FilterPipeline {
@@ -109,134 +269,330 @@ impl FilterPipeline {
             .iter()
             .fold(tasks, |acc, filter| filter.apply(acc))
     }
+
+    /// Returns whether a single task passes every filter, for use when streaming.
+    pub fn matches(&self, task: &Task) -> bool {
+        !self.apply(vec![task]).is_empty()
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Priority {
     Highest,
     High,
     Medium,
     Low,
     Lowest,
+    #[default]
     None, // Represents no specific priority
 }
 
-#[derive(Serialize)]
+impl Priority {
+    /// Reads a priority from its lowercase name, as written by [`Priority::as_str`].
+    pub fn from_name(name: &str) -> Option<Priority> {
+        [
+            Priority::Highest,
+            Priority::High,
+            Priority::Medium,
+            Priority::Low,
+            Priority::Lowest,
+            Priority::None,
+        ]
+        .into_iter()
+        .find(|priority| priority.as_str() == name.trim().to_lowercase())
+    }
+
+    /// Lowercase name of the priority, as used in JSON output.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Highest => "highest",
+            Priority::High => "high",
+            Priority::Medium => "medium",
+            Priority::Low => "low",
+            Priority::Lowest => "lowest",
+            Priority::None => "none",
+        }
+    }
+
+    /// The emoji that marks this priority in Markdown, or an empty string for `None`.
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            Priority::Highest => "🔺",
+            Priority::High => "⏫",
+            Priority::Medium => "🔼",
+            Priority::Low => "🔽",
+            Priority::Lowest => "⏬",
+            Priority::None => "",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Task {
     pub name: String,
     pub completed: bool,
+    /// Date the task was created (➕).
     #[serde(
         serialize_with = "serializers::serialize",
         deserialize_with = "serializers::deserialize",
-        skip_serializing_if = "Option::is_none"
+        default
+    )]
+    pub created: Option<NaiveDate>,
+    #[serde(
+        serialize_with = "serializers::serialize",
+        deserialize_with = "serializers::deserialize",
+        default
     )]
     pub due: Option<NaiveDate>,
+    /// Time of day following the due date, if any.
+    #[serde(
+        serialize_with = "time_serializers::serialize",
+        deserialize_with = "time_serializers::deserialize",
+        default
+    )]
+    pub due_time: Option<NaiveTime>,
     #[serde(
         serialize_with = "serializers::serialize",
         deserialize_with = "serializers::deserialize",
-        skip_serializing_if = "Option::is_none"
+        default
     )]
     pub scheduled: Option<NaiveDate>,
+    /// Time of day following the scheduled date, if any.
+    #[serde(
+        serialize_with = "time_serializers::serialize",
+        deserialize_with = "time_serializers::deserialize",
+        default
+    )]
+    pub scheduled_time: Option<NaiveTime>,
     #[serde(
         serialize_with = "serializers::serialize",
         deserialize_with = "serializers::deserialize",
-        skip_serializing_if = "Option::is_none"
+        default
     )]
     pub start: Option<NaiveDate>,
+    /// Time of day following the start date, if any.
+    #[serde(
+        serialize_with = "time_serializers::serialize",
+        deserialize_with = "time_serializers::deserialize",
+        default
+    )]
+    pub start_time: Option<NaiveTime>,
+    /// Completion date (✅).
+    #[serde(
+        serialize_with = "serializers::serialize",
+        deserialize_with = "serializers::deserialize",
+        default
+    )]
+    pub done: Option<NaiveDate>,
+    /// Time of day following the completion date, if any.
+    #[serde(
+        serialize_with = "time_serializers::serialize",
+        deserialize_with = "time_serializers::deserialize",
+        default
+    )]
+    pub done_time: Option<NaiveTime>,
+    #[serde(default)]
     pub overdue: bool,
+    /// Whether the start date (🛫) is still to come. Set by [`Task::update_date_flags`].
+    #[serde(default)]
+    pub not_started: bool,
+    /// Whether the task is due today. Set by [`Task::update_date_flags`].
+    #[serde(default)]
+    pub due_today: bool,
+    /// Whether the task is due today or in the next few days. Set by
+    /// [`Task::update_date_flags`].
+    #[serde(default)]
+    pub due_soon: bool,
     pub priority: Priority,
+    /// Recurrence rule following 🔁, such as "every week".
+    #[serde(default)]
+    pub recurrence: Option<String>,
+    /// Estimated effort in minutes, following ⏱ as in `⏱ 1h30m`.
+    #[serde(default)]
+    pub estimate: Option<u32>,
+    /// Urgency score from [`urgency::UrgencyModel::score`]; parsers leave it at 0.
+    #[serde(default)]
+    pub urgency: f64,
+    /// Identifier other tasks use to depend on this one (🆔).
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Identifiers of the tasks that must be done before this one (⛔).
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Whether a task it depends on is still open. This needs every task of the run, so
+    /// it is set once they have all been read and is left out of the JSON output.
+    #[serde(skip)]
+    pub blocked: bool,
+    /// Obsidian block ID ending the line (`^abc123`), without its caret.
+    #[serde(default)]
+    pub block_id: Option<String>,
+    /// Identifier that survives edits to the task's dates, status and position, as
+    /// given by [`Task::stable_id`] once the task's file is known.
+    #[serde(default)]
+    pub uid: Option<String>,
+    /// Tags without their leading `#` (Markdown) or `+` (todo.txt project).
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Contexts and assignees without their leading `@`, from todo.txt `@context` words
+    /// or Markdown `@mentions`.
+    #[serde(default)]
+    pub contexts: Vec<String>,
+    /// Wikilinks, Markdown links and embeds in the name, in order.
+    #[serde(default)]
+    pub links: Vec<Link>,
+    /// Indented lines and nested bullets below the task, as raw Markdown without the
+    /// list item's indentation.
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// Path of the file the task was read from, when it did not come from stdin.
+    #[serde(default)]
+    pub file: Option<String>,
+    /// 1-based line number of the task within its input.
+    #[serde(default)]
+    pub line: Option<usize>,
+    /// Line number of the task this one is nested under, within the same input.
+    #[serde(default)]
+    pub parent: Option<usize>,
 }
 
-fn clean_description(description: &str) -> String {
-    let re = Regex::new(r"\s+").unwrap(); // Matches one or more whitespace characters
-    re.replace_all(description.trim(), " ").to_string()
-}
-
-pub fn parse_priority(description: &str) -> (String, Priority) {
-    let (priority, signifier) = if description.contains("🔺") {
-        (Priority::Highest, "🔺")
-    } else if description.contains("⏫") {
-        (Priority::High, "⏫")
-    } else if description.contains("🔼") {
-        (Priority::Medium, "🔼")
-    } else if description.contains("🔽") {
-        (Priority::Low, "🔽")
-    } else if description.contains("⏬") {
-        (Priority::Lowest, "⏬")
-    } else {
-        (Priority::None, "")
-    };
-
-    // Remove the signifier from the description to clean it up
-    let clean_description = if !signifier.is_empty() {
-        description.replace(signifier, "").trim().to_string()
-    } else {
-        description.to_string()
-    };
-
-    (clean_description, priority)
-}
-
-/// Parses the input text into a vector of `Task` objects.
-pub fn parse_input(input: &str) -> Vec<Task> {
-    let task_regex = Regex::new(r"^\s*-\s*\[(\s|x)]\s*(.*)").unwrap();
-    let due_date_regex = Regex::new(r"📅 (\d{4}-\d{2}-\d{2})").unwrap();
-    let scheduled_date_regex = Regex::new(r"⏳ (\d{4}-\d{2}-\d{2})").unwrap();
-    let start_date_regex = Regex::new(r"🛫 (\d{4}-\d{2}-\d{2})").unwrap(); // Regex for start dates
-
-    input
-        .lines()
-        .filter_map(|line| {
-            task_regex.captures(line).map(|caps| {
-                let completed = caps.get(1).map_or(false, |m| m.as_str() == "x");
-                let mut name_with_potential_dates =
-                    caps.get(2).map_or("", |m| m.as_str()).to_string();
-
-                // Extract and parse the due date
-                let due = parse_date(&due_date_regex, &name_with_potential_dates);
-                // Extract and parse the scheduled date
-                let scheduled = parse_date(&scheduled_date_regex, &name_with_potential_dates);
-                // Extract and parse the start date
-                let start = parse_date(&start_date_regex, &name_with_potential_dates);
-
-                // Clean the task name by removing date strings
-                name_with_potential_dates = remove_date_strings(
-                    &[&due_date_regex, &scheduled_date_regex, &start_date_regex],
-                    name_with_potential_dates,
-                );
-
-                let overdue = due.map_or(false, |due_date| due_date < Local::today().naive_local());
-
-                let (description_without_priorities, priority) =
-                    parse_priority(&name_with_potential_dates);
-
-                // Clean up the remaining description
-                let cleaned_description = clean_description(&description_without_priorities);
-
-                Task {
-                    name: cleaned_description,
-                    completed,
-                    due,
-                    scheduled,
-                    start,
-                    overdue,
-                    priority: priority,
-                }
+impl Task {
+    /// Sets `not_started`, `due_today` and `due_soon` as of `today`, counting a task as
+    /// due soon from `due_soon_days` days before its due date. Completed tasks have none
+    /// of them. Parsers leave the flags unset.
+    pub fn update_date_flags(&mut self, today: NaiveDate, due_soon_days: u32) {
+        let open = !self.completed;
+        self.not_started = open && self.start.is_some_and(|start| start > today);
+        self.due_today = open && self.due == Some(today);
+        self.due_soon = open
+            && self.due.is_some_and(|due| {
+                due >= today && (due - today).num_days() <= i64::from(due_soon_days)
+            });
+    }
+
+    /// The due date and time, at midnight when no time is given.
+    pub fn due_at(&self) -> Option<NaiveDateTime> {
+        date_time(self.due, self.due_time)
+    }
+
+    /// The scheduled date and time, at midnight when no time is given.
+    pub fn scheduled_at(&self) -> Option<NaiveDateTime> {
+        date_time(self.scheduled, self.scheduled_time)
+    }
+
+    /// The start date and time, at midnight when no time is given.
+    pub fn start_at(&self) -> Option<NaiveDateTime> {
+        date_time(self.start, self.start_time)
+    }
+
+    /// The completion date and time, at midnight when no time is given.
+    pub fn done_at(&self) -> Option<NaiveDateTime> {
+        date_time(self.done, self.done_time)
+    }
+
+    /// The task's 🆔 or block ID when it has one, and otherwise a hash of its file and
+    /// normalised name, which does not change when its dates, priority, status or line do.
+    /// This is the ID of the first task with its name in the file; [`UidAssigner`] tells
+    /// later ones apart.
+    pub fn stable_id(&self) -> String {
+        self.stable_id_at(0)
+    }
+
+    /// As [`Task::stable_id`], for a task following `occurrence` others without an ID
+    /// that have the same name in the same file.
+    pub fn stable_id_at(&self, occurrence: usize) -> String {
+        match self.id.as_ref().or(self.block_id.as_ref()) {
+            Some(id) => id.clone(),
+            None => content_id(self.file.as_deref(), &self.name, occurrence),
+        }
+    }
+
+    /// The targets of the task's links, which may contain spaces, separated by commas.
+    pub(crate) fn link_targets(&self) -> String {
+        self.links
+            .iter()
+            .map(|link| link.target.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// The name without the inline `#tag` words of its tags, for formats that store
+    /// tags separately.
+    pub fn name_without_tags(&self) -> String {
+        self.name
+            .split_whitespace()
+            .filter(|word| {
+                !word
+                    .strip_prefix('#')
+                    .is_some_and(|tag| self.tags.iter().any(|t| t == tag))
             })
-        })
-        .collect()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
 }
 
-fn parse_date(date_regex: &Regex, text: &str) -> Option<NaiveDate> {
-    date_regex.captures(text).and_then(|caps| {
-        caps.get(1)
-            .and_then(|m| NaiveDate::parse_from_str(m.as_str(), "%Y-%m-%d").ok())
-    })
+/// A task read from another tool, with the identifier that tool gave it, if any, and
+/// notes that have no place on the task line.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportedTask {
+    pub uid: Option<String>,
+    pub task: Task,
+    pub annotations: Vec<String>,
+}
+
+/// Gives tasks their `uid` in the order they are read, numbering the tasks without an
+/// ID that repeat a name within a file as [`markdown::assign_block_ids`] does, so each
+/// gets a `uid` of its own.
+#[derive(Debug, Clone, Default)]
+pub struct UidAssigner {
+    /// How many tasks without an ID have been seen with each file and normalised name.
+    occurrences: HashMap<(Option<String>, String), usize>,
 }
 
-fn remove_date_strings(regexes: &[&Regex], mut text: String) -> String {
-    for regex in regexes {
-        text = regex.replace_all(&text, "").to_string();
+impl UidAssigner {
+    /// Sets the `uid` of `task`, read after every task passed before, unless it already
+    /// has one.
+    pub fn assign(&mut self, task: &mut Task) {
+        if task.uid.is_some() {
+            return;
+        }
+        let occurrence = if task.id.is_some() || task.block_id.is_some() {
+            0
+        } else {
+            let key = (task.file.clone(), normalise_name(&task.name));
+            let seen = self.occurrences.entry(key).or_insert(0);
+            *seen += 1;
+            *seen - 1
+        };
+        task.uid = Some(task.stable_id_at(occurrence));
     }
-    text
+}
+
+fn date_time(date: Option<NaiveDate>, time: Option<NaiveTime>) -> Option<NaiveDateTime> {
+    date.map(|date| date.and_time(time.unwrap_or(NaiveTime::MIN)))
+}
+
+/// Hashes a task's file and name, ignoring case and spacing, into a 12-digit hex ID.
+/// Tasks repeating a name within a file can be told apart by their `occurrence`.
+pub(crate) fn content_id(file: Option<&str>, name: &str, occurrence: usize) -> String {
+    let mut source = format!("{}\n{}", file.unwrap_or(""), normalise_name(name));
+    if occurrence > 0 {
+        source.push_str(&format!("\n{}", occurrence));
+    }
+    format!("{:012x}", fnv1a(source.as_bytes()) >> 16)
+}
+
+/// Lowercases `name` and collapses its spacing, so edits to either keep its ID.
+fn normalise_name(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// 64-bit FNV-1a, used for identifiers that must not change between releases.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
 }
@@ -0,0 +1,102 @@
+//! Links, wikilinks and embeds in task text.
+//!
+//! Three kinds are recognised:
+//!
+//! - wikilinks, `[[Meeting 2024-02-01]]` or `[[Meeting 2024-02-01|standup]]`;
+//! - Markdown links, `[spec](https://example.com/spec)`;
+//! - embeds, `![[diagram.png]]` or `![diagram](diagram.png)`.
+//!
+//! Links stay in the task name, as Obsidian shows them inline; [`strip`] replaces each
+//! one with the text it is displayed as.
+
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// How a link is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkKind {
+    Wikilink,
+    Markdown,
+    Embed,
+}
+
+/// A link found in a task's name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Link {
+    /// The note, heading or URL linked to, as written.
+    pub target: String,
+    /// The text shown instead of the target, if any.
+    #[serde(default)]
+    pub alias: Option<String>,
+    pub kind: LinkKind,
+}
+
+impl Link {
+    /// The name of the note linked to, without folders, heading or `.md` extension, or
+    /// `None` for links to web pages.
+    pub fn note(&self) -> Option<&str> {
+        if self.target.contains("://") || self.target.starts_with("mailto:") {
+            return None;
+        }
+        Some(note_name(&self.target)).filter(|name| !name.is_empty())
+    }
+
+    /// The text Obsidian displays for the link.
+    pub fn display(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.target)
+    }
+}
+
+/// Compiled once, as every task's name is searched for links.
+fn link_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r#"(!?)\[\[([^\[\]|]+)(?:\|([^\[\]]*))?\]\]|(!?)\[([^\[\]]*)\]\(<?([^()\s<>]+)>?(?:\s+"[^"]*")?\)"#,
+        )
+        .unwrap()
+    })
+}
+
+/// Returns the links in `text`, in the order they appear.
+pub fn extract(text: &str) -> Vec<Link> {
+    link_regex()
+        .captures_iter(text)
+        .map(|caps| link(&caps))
+        .collect()
+}
+
+/// Replaces every link in `text` with the text it is displayed as.
+pub fn strip(text: &str) -> String {
+    link_regex()
+        .replace_all(text, |caps: &Captures| link(caps).display().to_string())
+        .to_string()
+}
+
+/// Reduces a note reference, such as `Projects/Spec.md#Scope` or `Spec`, to its note
+/// name, `Spec`.
+pub fn note_name(reference: &str) -> &str {
+    let reference = reference.split(['#', '^']).next().unwrap_or("").trim();
+    let name = reference.rsplit(['/', '\\']).next().unwrap_or(reference);
+    name.strip_suffix(".md").unwrap_or(name)
+}
+
+fn link(caps: &Captures) -> Link {
+    let (embed, target, alias, kind) = match caps.get(2) {
+        Some(target) => (&caps[1], target.as_str(), caps.get(3), LinkKind::Wikilink),
+        None => (&caps[4], &caps[6], caps.get(5), LinkKind::Markdown),
+    };
+    Link {
+        target: target.trim().to_string(),
+        alias: alias
+            .map(|alias| alias.as_str().trim().to_string())
+            .filter(|alias| !alias.is_empty()),
+        kind: if embed.is_empty() {
+            kind
+        } else {
+            LinkKind::Embed
+        },
+    }
+}
@@ -0,0 +1,228 @@
+//! Logseq graph input.
+//!
+//! Blocks that start with a task marker are tasks:
+//!
+//! ```text
+//! - TODO [#A] Write spec #work SCHEDULED: <2024-02-10 Sat>
+//!   deadline:: [[2024-02-12]]
+//!   - LATER Collect feedback
+//!     DEADLINE: <2024-02-09 Fri .+1w>
+//! ```
+//!
+//! `DONE`, `CANCELED` and `CANCELLED` mark a task as completed; `TODO`, `LATER`, `NOW`,
+//! `DOING`, `IN-PROGRESS`, `WAIT` and `WAITING` leave it open. Priorities, `SCHEDULED`
+//! and `DEADLINE` timestamps follow Org-mode, on the block line or the lines below it.
+//! The `deadline::`, `scheduled::`, `tags::` and `id::` properties are read too, with
+//! dates written as `2024-02-12` or as journal links such as `[[Feb 12th, 2024]]`, and
+//! `id::` giving the block ID. A task nested under another records that task's line as
+//! its parent, and page references such as `[[Spec]]` become its links.
+
+use crate::error::Result;
+use crate::org::{parse_timestamp, priority_from_cookie};
+use crate::{links, Diagnostic, ParsedInput, Priority, Task};
+use chrono::{Local, NaiveDate};
+use regex::Regex;
+use std::io::Read;
+
+/// Markers that make a block a task, paired with whether they complete it.
+const MARKERS: [(&str, bool); 10] = [
+    ("TODO", false),
+    ("LATER", false),
+    ("NOW", false),
+    ("DOING", false),
+    ("IN-PROGRESS", false),
+    ("WAIT", false),
+    ("WAITING", false),
+    ("DONE", true),
+    ("CANCELED", true),
+    ("CANCELLED", true),
+];
+
+/// Parses a Logseq page into tasks, one per block with a task marker.
+pub fn parse(input: &str) -> ParsedInput {
+    let block_regex = Regex::new(r"^(\s*)-(?:\s+(.*))?$").unwrap();
+    let priority_regex = Regex::new(r"\s*\[#([A-Z])\]").unwrap();
+    let planning_regex = Regex::new(r"(DEADLINE|SCHEDULED):\s*<([^>]*)>").unwrap();
+    let property_regex = Regex::new(r"^\s*([\w-]+)::\s*(.*?)\s*$").unwrap();
+    let tag_regex = Regex::new(r"(?:^|\s)#(?:\[\[([^\]]+)\]\]|([^\s#\[\],]+))").unwrap();
+    let ordinal_regex = Regex::new(r"^(\w+ \d{1,2})(?:st|nd|rd|th), (\d{4})$").unwrap();
+    let today = Local::now().date_naive();
+
+    let mut tasks: Vec<Task> = Vec::new();
+    let mut diagnostics = Vec::new();
+    // Enclosing blocks by indentation, with the line of those that are tasks
+    let mut ancestors: Vec<(usize, Option<usize>)> = Vec::new();
+    // Index in `tasks` of the task whose block the following lines belong to
+    let mut current: Option<usize> = None;
+
+    for (index, line) in input.lines().enumerate() {
+        let line_number = index + 1;
+        if let Some(caps) = block_regex.captures(line) {
+            let indent = caps[1].chars().count();
+            while ancestors.last().is_some_and(|&(depth, _)| depth >= indent) {
+                ancestors.pop();
+            }
+            let parent = ancestors.iter().rev().find_map(|&(_, line)| line);
+
+            let content = caps.get(2).map_or("", |m| m.as_str());
+            let Some((marker, completed)) = marker(content) else {
+                ancestors.push((indent, None));
+                current = None;
+                continue;
+            };
+            ancestors.push((indent, Some(line_number)));
+
+            let mut title = content[marker.len()..].to_string();
+            let mut priority = Priority::None;
+            if let Some(cookie) = priority_regex.captures(&title) {
+                priority = priority_from_cookie(&cookie[1]);
+                title = priority_regex.replace(&title, "").to_string();
+            }
+            let mut task = Task {
+                completed,
+                priority,
+                tags: inline_tags(&tag_regex, &title),
+                line: Some(line_number),
+                parent,
+                ..Default::default()
+            };
+
+            // Planning may trail the block title, as Logseq writes it for single lines
+            let title_offset = caps.get(2).unwrap().start() + marker.len();
+            if let Some(found) = planning_regex.find(&title) {
+                apply_planning(
+                    &planning_regex,
+                    &mut task,
+                    line_number,
+                    line,
+                    title_offset,
+                    &mut diagnostics,
+                );
+                title.truncate(found.start());
+            }
+
+            task.name = title.split_whitespace().collect::<Vec<_>>().join(" ");
+            task.links = links::extract(&task.name);
+            tasks.push(task);
+            current = Some(tasks.len() - 1);
+            continue;
+        }
+
+        let Some(task) = current.map(|i| &mut tasks[i]) else {
+            continue;
+        };
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("SCHEDULED:") || trimmed.starts_with("DEADLINE:") {
+            let from = line.len() - trimmed.len();
+            apply_planning(
+                &planning_regex,
+                task,
+                line_number,
+                line,
+                from,
+                &mut diagnostics,
+            );
+        } else if let Some(caps) = property_regex.captures(line) {
+            let value = caps.get(2).unwrap();
+            let column = line[..value.start()].chars().count() + 1;
+            match caps[1].to_lowercase().as_str() {
+                key @ ("deadline" | "scheduled") => {
+                    match parse_date(&ordinal_regex, value.as_str()) {
+                        Some(date) if key == "deadline" => task.due = Some(date),
+                        Some(date) => task.scheduled = Some(date),
+                        None => diagnostics.push(Diagnostic {
+                            line: line_number,
+                            column,
+                            message: format!("invalid {} date `{}`", key, value.as_str()),
+                        }),
+                    }
+                }
+                "id" if !value.as_str().is_empty() => {
+                    task.block_id = Some(value.as_str().to_string());
+                }
+                "tags" => {
+                    for tag in value.as_str().split(',') {
+                        let tag = tag.trim().trim_start_matches('#');
+                        let tag = tag.trim_start_matches("[[").trim_end_matches("]]");
+                        if !tag.is_empty() && !task.tags.iter().any(|t| t == tag) {
+                            task.tags.push(tag.to_string());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    for task in &mut tasks {
+        task.overdue = !task.completed && task.due.is_some_and(|due| due < today);
+    }
+    ParsedInput { tasks, diagnostics }
+}
+
+/// Reads all of `reader` and parses it as a Logseq page.
+pub fn read_tasks<R: Read>(mut reader: R) -> Result<ParsedInput> {
+    let mut input = String::new();
+    reader.read_to_string(&mut input)?;
+    Ok(parse(&input))
+}
+
+/// Returns the task marker starting `content`, if any, and whether it completes the task.
+fn marker(content: &str) -> Option<(&'static str, bool)> {
+    MARKERS.into_iter().find(|(marker, _)| {
+        content
+            .strip_prefix(marker)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+    })
+}
+
+/// Sets the dates in the `SCHEDULED` and `DEADLINE` timestamps found from byte `from`
+/// of `line`, reporting those that cannot be read.
+fn apply_planning(
+    regex: &Regex,
+    task: &mut Task,
+    line_number: usize,
+    line: &str,
+    from: usize,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for caps in regex.captures_iter(&line[from..]) {
+        let stamp = caps.get(2).unwrap();
+        let Some((date, time, repeater)) = parse_timestamp(stamp.as_str()) else {
+            diagnostics.push(Diagnostic {
+                line: line_number,
+                column: line[..from + stamp.start()].chars().count() + 1,
+                message: format!("invalid timestamp `<{}>`", stamp.as_str()),
+            });
+            continue;
+        };
+        if &caps[1] == "DEADLINE" {
+            (task.due, task.due_time) = (Some(date), time);
+        } else {
+            (task.scheduled, task.scheduled_time) = (Some(date), time);
+        }
+        if let Some(repeater) = repeater {
+            task.recurrence = task.recurrence.take().or(Some(repeater));
+        }
+    }
+}
+
+/// Tags written inline as `#tag` or `#[[multi word tag]]`.
+fn inline_tags(regex: &Regex, title: &str) -> Vec<String> {
+    regex
+        .captures_iter(title)
+        .filter_map(|caps| caps.get(1).or_else(|| caps.get(2)))
+        .map(|tag| tag.as_str().to_string())
+        .collect()
+}
+
+/// Reads a property date, either `2024-02-12` or a journal link such as
+/// `[[Feb 12th, 2024]]`.
+fn parse_date(ordinal_regex: &Regex, value: &str) -> Option<NaiveDate> {
+    let value = value.trim_start_matches("[[").trim_end_matches("]]");
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Some(date);
+    }
+    let caps = ordinal_regex.captures(value)?;
+    NaiveDate::parse_from_str(&format!("{} {}", &caps[1], &caps[2]), "%b %d %Y").ok()
+}
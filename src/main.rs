@@ -1,8 +1,34 @@
-use clap::{App, Arg};
-use marktask::{parse_input, DateRangeFilter, FilterPipeline, OverdueFilter, Task};
-use serde_json;
-use std::io::{self, Read};
-pub mod dates;
+use chrono::{Local, NaiveDateTime, Utc};
+use clap::{App, Arg, ArgMatches};
+use marktask::color::ColorChoice;
+use marktask::config::Config;
+use marktask::markdown::Syntax;
+use marktask::urgency::UrgencyModel;
+use marktask::{
+    csv, dates, dependencies, ics, json, links, logseq, markdown, mentions, org, parse_input,
+    parse_reader, table, taskwarrior, template::Template, todotxt, ActiveFilter, BlockedFilter,
+    ContextFilter, DateRangeFilter, Diagnostic, Error, FilterPipeline, ImportedTask, LinkFilter,
+    OverdueFilter, ParsedInput, ScheduledFilter, Task, TaskReader, UidAssigner,
+};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Write};
+use std::path::Path;
+use std::process;
+
+/// The text listing of `marktask next`, showing each task's urgency.
+const NEXT_TEMPLATE: &str = "{urgency} {status} - {name}";
+
+/// Exit status for invalid command-line arguments (sysexits' `EX_USAGE`).
+const EXIT_USAGE: i32 = 64;
+/// Exit status for malformed input data (sysexits' `EX_DATAERR`).
+const EXIT_DATA: i32 = 65;
+/// Exit status for internal failures such as serialization (sysexits' `EX_SOFTWARE`).
+const EXIT_SOFTWARE: i32 = 70;
+/// Exit status for failures reading input or writing output (sysexits' `EX_IOERR`).
+const EXIT_IO: i32 = 74;
+/// Exit status for an unusable configuration file (sysexits' `EX_CONFIG`).
+const EXIT_CONFIG: i32 = 78;
 
 fn main() {
     let matches = App::new("marktask")
@@ -10,14 +36,65 @@ fn main() {
         .about("Processes Markdown tasks")
         .arg(Arg::with_name("json")
             .long("json")
-            .help("Outputs the tasks in JSON format")
+            .help("Outputs the tasks in JSON format. Shorthand for `--format json`.")
+            .takes_value(false)
+            .conflicts_with("format"))
+        .arg(Arg::with_name("format")
+            .long("format")
+            .value_name("FORMAT")
+            .help("Output format. `ndjson` writes one task per line as soon as it is parsed.")
+            .takes_value(true)
+            .possible_values(["text", "table", "json", "ndjson", "csv", "tsv", "ics", "todotxt", "taskwarrior", "org", "dot"])
+            .default_value("text"))
+        .arg(Arg::with_name("with-notes")
+            .long("with-notes")
+            .help("Prints each task's notes below it in text output")
+            .takes_value(false))
+        .arg(Arg::with_name("columns")
+            .long("columns")
+            .value_name("COLUMNS")
+            .help("Comma-separated columns for CSV/TSV output, e.g. name,due,priority,file,line")
+            .takes_value(true))
+        .arg(Arg::with_name("json-schema")
+            .long("json-schema")
+            .help("Prints the JSON Schema for the `--json` output and exits")
             .takes_value(false))
+        .arg(Arg::with_name("input-format")
+            .long("input-format")
+            .value_name("FORMAT")
+            .help("Format of the tasks read from stdin. `json` accepts the output of `--json`.")
+            .takes_value(true)
+            .possible_values(["markdown", "json", "todotxt", "org", "logseq"])
+            .default_value("markdown"))
+        .arg(Arg::with_name("template")
+            .long("template")
+            .value_name("TEMPLATE")
+            .help("Template for text output, e.g. '{status} {priority_emoji} {name}{?due} (due {due:%a %d %b}){/due}'")
+            .takes_value(true)
+            .conflicts_with("template-file"))
+        .arg(Arg::with_name("template-file")
+            .long("template-file")
+            .value_name("PATH")
+            .help("Reads the text output template from a file")
+            .takes_value(true))
+        .arg(Arg::with_name("color")
+            .long("color")
+            .value_name("WHEN")
+            .help("Colours text and table output. `auto` colours terminals unless NO_COLOR is set.")
+            .takes_value(true)
+            .possible_values(["auto", "always", "never"])
+            .default_value("auto"))
+        .arg(Arg::with_name("config")
+            .long("config")
+            .value_name("PATH")
+            .help("Configuration file. Defaults to $MARKTASK_CONFIG or ~/.config/marktask/config.toml")
+            .takes_value(true))
         .arg(Arg::with_name("overdue")
             .long("overdue")
             .value_name("BOOLEAN")
             .help("Filters tasks based on their overdue status. Defaults to true, showing all tasks.")
             .takes_value(true)
-            .possible_values(&["true", "false"]))
+            .possible_values(["true", "false"]))
         .arg(Arg::with_name("from")
             .long("from")
             .value_name("DATE")
@@ -28,18 +105,161 @@ fn main() {
             .value_name("DATE")
             .help("Include tasks up to this date (inclusive). Format: YYYY-MM-DD")
             .takes_value(true))
+        .arg(Arg::with_name("scheduled")
+            .long("scheduled")
+            .value_name("DATE")
+            .help("Only includes tasks scheduled (⏳) on this date. Format: YYYY-MM-DD, today or +1d")
+            .takes_value(true))
+        .arg(Arg::with_name("include-future")
+            .long("include-future")
+            .help("Includes tasks whose start (🛫) or scheduled (⏳) date is still to come. Implied by --from, --to, --scheduled, the export command and the formats it writes: taskwarrior, todotxt and ics")
+            .takes_value(false))
+        .arg(Arg::with_name("due-soon-days")
+            .long("due-soon-days")
+            .value_name("DAYS")
+            .help("Days before the due date from which tasks count as due soon")
+            .takes_value(true)
+            .default_value("3")
+            .validator(|value| value.parse::<u32>()))
+        .arg(Arg::with_name("summary")
+            .long("summary")
+            .help("Ends text and table output with the number of tasks and their total estimate (⏱)")
+            .takes_value(false))
+        .arg(Arg::with_name("links-to")
+            .long("links-to")
+            .value_name("NOTE")
+            .help("Only includes tasks that link to or embed this note")
+            .takes_value(true))
+        .arg(Arg::with_name("context")
+            .long("context")
+            .visible_alias("assignee")
+            .value_name("NAME")
+            .help("Only includes tasks mentioning this person or context, e.g. alice or @phone")
+            .takes_value(true))
+        .arg(Arg::with_name("group-by")
+            .long("group-by")
+            .value_name("FIELD")
+            .help("Lists text and table output under a heading for each person or context mentioned")
+            .takes_value(true)
+            .possible_values(["context", "assignee"]))
+        .arg(Arg::with_name("blocked")
+            .long("blocked")
+            .help("Only includes open tasks waiting on another open task (⛔)")
+            .takes_value(false)
+            .conflicts_with("actionable"))
+        .arg(Arg::with_name("actionable")
+            .long("actionable")
+            .help("Only includes open tasks that no open task blocks")
+            .takes_value(false))
+        .arg(Arg::with_name("strip-links")
+            .long("strip-links")
+            .help("Shows links in task names as their alias, or their target when they have none")
+            .takes_value(false))
+        .arg(Arg::with_name("strip-mentions")
+            .long("strip-mentions")
+            .help("Removes @mentions from task names")
+            .takes_value(false))
+        .arg(files_arg())
+        .subcommand(App::new("import")
+            .about("Converts tasks from another format into Markdown task lines")
+            .arg(Arg::with_name("source")
+                .value_name("FILE")
+                .help("File to import tasks from")
+                .required(true))
+            .arg(Arg::with_name("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Format of the imported file. Defaults to one matching its extension: .ics, .txt or .json (Taskwarrior).")
+                .takes_value(true)
+                .possible_values(["ics", "todotxt", "taskwarrior"]))
+            .arg(Arg::with_name("into")
+                .long("into")
+                .value_name("FILE")
+                .help("Appends the tasks to this Markdown file instead of printing them")
+                .takes_value(true))
+            .arg(Arg::with_name("dedup")
+                .long("dedup")
+                .help("Skips tasks whose UID or name matches a task already in the --into file")
+                .requires("into")))
+        .subcommand(App::new("export")
+            .about("Converts Markdown tasks into another tool's format")
+            .arg(Arg::with_name("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Format to export to")
+                .takes_value(true)
+                .possible_values(["taskwarrior", "todotxt", "ics"])
+                .default_value("taskwarrior"))
+            .arg(files_arg()))
+        .subcommand(App::new("convert")
+            .about("Rewrites task lines with emoji markers or Dataview inline fields")
+            .arg(Arg::with_name("to")
+                .long("to")
+                .value_name("SYNTAX")
+                .help("Syntax to convert the task lines to")
+                .takes_value(true)
+                .required(true)
+                .possible_values(["emoji", "dataview"]))
+            .arg(Arg::with_name("in-place")
+                .long("in-place")
+                .help("Rewrites the files instead of printing the converted text")
+                .requires("files"))
+            .arg(files_arg()))
+        .subcommand(App::new("assign-ids")
+            .about("Appends a block ID (^id) to task lines that have no 🆔 or block ID yet")
+            .arg(Arg::with_name("in-place")
+                .long("in-place")
+                .help("Rewrites the files instead of printing the updated text")
+                .requires("files"))
+            .arg(files_arg()))
+        .subcommand(App::new("next")
+            .about("Shows the most urgent open tasks that no open task blocks")
+            .arg(Arg::with_name("count")
+                .short('n')
+                .long("count")
+                .value_name("N")
+                .help("Number of tasks to show")
+                .takes_value(true)
+                .default_value("10")
+                .validator(|value| value.parse::<usize>()))
+            .arg(files_arg()))
+        .subcommand(App::new("lint")
+            .about("Reports malformed dates, duplicate date markers, unknown status symbols and broken dependencies")
+            .arg(files_arg()))
         .get_matches();
 
-    let mut input = String::new();
-    io::stdin()
-        .read_to_string(&mut input)
-        .expect("Failed to read from stdin");
+    if let Err(err) = run(&matches) {
+        match err {
+            // The reader went away (e.g. `marktask | head`); nothing left to report.
+            Error::Io(ref io_err) if io_err.kind() == io::ErrorKind::BrokenPipe => {}
+            _ => eprintln!("marktask: {}", err),
+        }
+        process::exit(exit_code(&err));
+    }
+}
+
+fn exit_code(err: &Error) -> i32 {
+    match err {
+        Error::InvalidDate(_) | Error::InvalidColumn(_) | Error::Template(_) => EXIT_USAGE,
+        Error::Parse(_) | Error::UnsupportedVersion(_) | Error::InvalidJson(_) => EXIT_DATA,
+        Error::Json(_) => EXIT_SOFTWARE,
+        Error::Config(_) => EXIT_CONFIG,
+        Error::Io(io_err) if io_err.kind() == io::ErrorKind::BrokenPipe => 0,
+        Error::Io(_) => EXIT_IO,
+    }
+}
 
-    let tasks = parse_input(&input);
-    let task_refs: Vec<&Task> = tasks.iter().collect();
+fn run(matches: &ArgMatches) -> Result<(), Error> {
+    // Validate arguments before blocking on stdin
+    let from_date = dates::parse_date_arg(matches.value_of("from"))?;
+    let to_date = dates::parse_date_arg(matches.value_of("to"))?;
+    let scheduled_date = dates::parse_date_arg(matches.value_of("scheduled"))?;
 
-    let from_date = dates::parse_date_arg(matches.value_of("from"));
-    let to_date = dates::parse_date_arg(matches.value_of("to"));
+    if matches.is_present("json-schema") {
+        let schema = json::schema(json::SCHEMA_VERSION).expect("current version has a schema");
+        io::stdout().lock().write_all(schema.as_bytes())?;
+        return Ok(());
+    }
 
     // Initialize the filter pipeline
     let mut pipeline = FilterPipeline::new();
@@ -55,23 +275,522 @@ fn main() {
         let date_range_filter = DateRangeFilter { from_date, to_date };
         pipeline.add_filter(Box::new(date_range_filter));
     }
+    if let Some(date) = scheduled_date {
+        pipeline.add_filter(Box::new(ScheduledFilter { date }));
+    }
+    if let Some(context) = matches.value_of("context") {
+        pipeline.add_filter(Box::new(ContextFilter {
+            context: context.to_string(),
+        }));
+    }
+    if let Some(note) = matches.value_of("links-to") {
+        pipeline.add_filter(Box::new(LinkFilter {
+            note: note.to_string(),
+        }));
+    }
+    let group_by = matches.is_present("group-by");
+    let next_matches = matches.subcommand_matches("next");
+    // Dependencies can only be resolved once every input has been read
+    let dependency_filter = match (
+        matches.is_present("blocked"),
+        matches.is_present("actionable"),
+    ) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ if next_matches.is_some() => Some(false),
+        _ => None,
+    };
 
-    // Apply the pipeline filters
-    let filtered_tasks = pipeline.apply(task_refs);
+    let export_matches = matches.subcommand_matches("export");
+    let format = if let Some(export_matches) = export_matches {
+        export_matches.value_of("format").unwrap_or("taskwarrior")
+    } else if matches.is_present("json") {
+        "json"
+    } else {
+        matches.value_of("format").unwrap_or("text")
+    };
+    // Tasks that have not started are hidden, unless dates are asked for explicitly or
+    // tasks are handed to another tool, whether through export or --format, as --help says
+    let include_future = matches.is_present("include-future")
+        || from_date.is_some()
+        || to_date.is_some()
+        || scheduled_date.is_some()
+        || matches!(format, "taskwarrior" | "todotxt" | "ics");
+    if !include_future {
+        pipeline.add_filter(Box::new(ActiveFilter {
+            today: Local::now().date_naive(),
+        }));
+    }
+    let input_format = matches.value_of("input-format").unwrap_or("markdown");
+    let config = Config::load(matches.value_of("config").map(Path::new))?;
+    let color_choice: ColorChoice = matches
+        .value_of("color")
+        .unwrap_or("auto")
+        .parse()
+        .unwrap_or(ColorChoice::Auto);
+    let theme = color_choice
+        .enabled(io::stdout().is_terminal())
+        .then_some(&config.colors);
+    let today = Local::now().date_naive();
+    let template = match (
+        matches.value_of("template"),
+        matches.value_of("template-file"),
+    ) {
+        (Some(source), _) => Template::parse(source)?,
+        (None, Some(path)) => {
+            let source = fs::read_to_string(path)
+                .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path, err)))?;
+            Template::parse(source.strip_suffix('\n').unwrap_or(&source))?
+        }
+        (None, None) if next_matches.is_some() => Template::parse(NEXT_TEMPLATE)?,
+        (None, None) => Template::default(),
+    };
+    let presentation = Presentation {
+        strip_links: matches.is_present("strip-links"),
+        strip_mentions: matches.is_present("strip-mentions"),
+        urgency: &config.urgency,
+        due_soon_days: matches
+            .value_of("due-soon-days")
+            .and_then(|days| days.parse().ok())
+            .unwrap_or(3),
+        now: Local::now().naive_local(),
+    };
+    let columns = match matches.value_of("columns") {
+        Some(spec) => csv::parse_columns(spec)?,
+        None => csv::DEFAULT_COLUMNS.to_vec(),
+    };
+
+    if let Some(import_matches) = matches.subcommand_matches("import") {
+        return import(import_matches, config.markdown.syntax);
+    }
+    if let Some(convert_matches) = matches.subcommand_matches("convert") {
+        let syntax: Syntax = convert_matches.value_of("to").unwrap_or("emoji").parse()?;
+        return rewrite(convert_matches, |text, _| markdown::convert(text, syntax));
+    }
+    if let Some(assign_matches) = matches.subcommand_matches("assign-ids") {
+        return rewrite(assign_matches, markdown::assign_block_ids);
+    }
+
+    let lint_matches = matches.subcommand_matches("lint");
+    let inputs = inputs(
+        lint_matches
+            .or(export_matches)
+            .or(next_matches)
+            .unwrap_or(matches),
+    );
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    let mut uids = UidAssigner::default();
+
+    // Stream Markdown straight through when writing NDJSON, so memory use stays constant
+    if format == "ndjson"
+        && input_format == "markdown"
+        && lint_matches.is_none()
+        && dependency_filter.is_none()
+    {
+        for input in &inputs {
+            for task in TaskReader::new(input.open()?) {
+                let task = presentation.present(input.attribute(task?, &mut uids));
+                if pipeline.matches(&task) {
+                    json::write_task_line(&mut out, &task)?;
+                }
+            }
+        }
+        return Ok(());
+    }
 
-    // Output logic based on the presence of the `--json` flag
-    if matches.is_present("json") {
-        println!(
-            "{}",
-            serde_json::to_string(&filtered_tasks).expect("Failed to serialize tasks")
+    let mut tasks = Vec::new();
+    let mut diagnostics = Vec::new();
+    for input in &inputs {
+        let reader = input.open()?;
+        let parsed = match input_format {
+            "json" => ParsedInput {
+                tasks: json::read_tasks(reader)?,
+                diagnostics: Vec::new(),
+            },
+            "todotxt" => todotxt::read_tasks(reader)?,
+            "org" => org::read_tasks(reader)?,
+            "logseq" => logseq::read_tasks(reader)?,
+            _ => parse_reader(reader)?,
+        };
+        if lint_matches.is_some() {
+            for diagnostic in &parsed.diagnostics {
+                match &input.file {
+                    Some(file) => writeln!(out, "{}:{}", file, diagnostic)?,
+                    None => writeln!(out, "{}", diagnostic)?,
+                }
+            }
+        }
+        diagnostics.extend(parsed.diagnostics);
+        tasks.extend(
+            parsed
+                .tasks
+                .into_iter()
+                .map(|task| presentation.present(input.attribute(task, &mut uids))),
         );
+    }
+
+    // Dependencies resolve across every file read
+    let open_ids = dependencies::open_ids(&tasks.iter().collect::<Vec<_>>());
+    for task in &mut tasks {
+        task.blocked = dependencies::is_blocked(task, &open_ids);
+    }
+    let task_refs: Vec<&Task> = tasks.iter().collect();
+
+    if lint_matches.is_some() {
+        for problem in dependencies::check(&task_refs) {
+            let task = task_refs[problem.task];
+            let diagnostic = Diagnostic {
+                line: task.line.unwrap_or(0),
+                column: 1,
+                message: problem.message,
+            };
+            match &task.file {
+                Some(file) => writeln!(out, "{}:{}", file, diagnostic)?,
+                None => writeln!(out, "{}", diagnostic)?,
+            }
+            diagnostics.push(diagnostic);
+        }
+        return ParsedInput {
+            tasks: Vec::new(),
+            diagnostics,
+        }
+        .strict()
+        .map(|_| ());
+    }
+
+    if let Some(show_blocked) = dependency_filter {
+        pipeline.add_filter(Box::new(BlockedFilter {
+            open_ids,
+            show_blocked,
+        }));
+    }
+
+    // Apply the pipeline filters
+    let mut filtered_tasks = pipeline.apply(task_refs);
+    if let Some(next_matches) = next_matches {
+        let count = next_matches
+            .value_of("count")
+            .unwrap_or("10")
+            .parse()
+            .unwrap_or(10);
+        filtered_tasks.sort_by(|a, b| b.urgency.total_cmp(&a.urgency));
+        filtered_tasks.truncate(count);
+    }
+    let summary = matches
+        .is_present("summary")
+        .then(|| summary(&filtered_tasks));
+
+    match format {
+        "json" => json::write_tasks(&mut out, &filtered_tasks)?,
+        "ndjson" => {
+            for task in filtered_tasks {
+                json::write_task_line(&mut out, task)?;
+            }
+        }
+        "csv" | "tsv" => {
+            let delimiter = if format == "csv" {
+                csv::Delimiter::Comma
+            } else {
+                csv::Delimiter::Tab
+            };
+            csv::write_tasks(&mut out, &filtered_tasks, &columns, delimiter)?;
+        }
+        "ics" => ics::write_calendar(&mut out, &filtered_tasks, Utc::now())?,
+        "todotxt" => todotxt::write_tasks(&mut out, &filtered_tasks)?,
+        "taskwarrior" => taskwarrior::write_tasks(&mut out, &filtered_tasks)?,
+        "org" => org::write_tasks(&mut out, &filtered_tasks)?,
+        "dot" => dependencies::write_dot(&mut out, &filtered_tasks)?,
+        "table" => {
+            let width = table::terminal_width();
+            for (index, (context, tasks)) in groups(&filtered_tasks, group_by).iter().enumerate() {
+                if group_by {
+                    write_heading(&mut out, index, context.as_deref())?;
+                }
+                table::write_tasks(&mut out, tasks, today, width, theme)?;
+            }
+            if let Some(summary) = summary {
+                writeln!(out, "{}", summary)?;
+            }
+        }
+        _ => {
+            let with_notes = matches.is_present("with-notes");
+            for (index, (context, tasks)) in groups(&filtered_tasks, group_by).iter().enumerate() {
+                if group_by {
+                    write_heading(&mut out, index, context.as_deref())?;
+                }
+                for &task in tasks {
+                    let line = template.render(task);
+                    match theme {
+                        Some(theme) => writeln!(out, "{}", theme.paint(task, &line))?,
+                        None => writeln!(out, "{}", line)?,
+                    }
+                    if with_notes {
+                        for note in task.notes.iter().flat_map(|notes| notes.lines()) {
+                            writeln!(out, "    {}", note)?;
+                        }
+                    }
+                }
+            }
+            if let Some(summary) = summary {
+                writeln!(out, "{}", summary)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Splits `tasks` by the people and contexts they mention when grouping, and otherwise
+/// returns them as a single group.
+fn groups<'a>(tasks: &[&'a Task], group_by: bool) -> Vec<(Option<String>, Vec<&'a Task>)> {
+    if group_by {
+        mentions::group(tasks)
     } else {
-        for task in filtered_tasks {
-            println!(
-                "{} - {}",
-                if task.completed { "[x]" } else { "[ ]" },
-                task.name
-            );
+        vec![(None, tasks.to_vec())]
+    }
+}
+
+/// Writes the `@context` heading of a group, after a blank line unless it is the first.
+fn write_heading<W: Write>(mut out: W, index: usize, context: Option<&str>) -> io::Result<()> {
+    if index > 0 {
+        writeln!(out)?;
+    }
+    match context {
+        Some(context) => writeln!(out, "@{}", context),
+        None => writeln!(out, "(no mentions)"),
+    }
+}
+
+/// Counts `tasks` and totals their estimates, as in `3 tasks, 2h15m estimated`.
+fn summary(tasks: &[&Task]) -> String {
+    let minutes: u32 = tasks.iter().filter_map(|task| task.estimate).sum();
+    let noun = if tasks.len() == 1 { "task" } else { "tasks" };
+    format!(
+        "{} {}, {} estimated",
+        tasks.len(),
+        noun,
+        dates::format_duration(minutes)
+    )
+}
+
+/// How tasks read from the inputs are shown.
+struct Presentation<'a> {
+    strip_links: bool,
+    strip_mentions: bool,
+    urgency: &'a UrgencyModel,
+    due_soon_days: u32,
+    now: NaiveDateTime,
+}
+
+impl Presentation<'_> {
+    /// Scores `task`, sets its date flags and applies the display options that change
+    /// its name.
+    fn present(&self, mut task: Task) -> Task {
+        task.urgency = self.urgency.score(&task, self.now);
+        task.update_date_flags(self.now.date(), self.due_soon_days);
+        if self.strip_links {
+            task.name = links::strip(&task.name);
+        }
+        if self.strip_mentions {
+            task.name = mentions::strip(&task.name);
+        }
+        task
+    }
+}
+
+/// Converts a calendar, todo.txt file or Taskwarrior export into Markdown tasks,
+/// printing them or appending them to a file.
+fn import(matches: &ArgMatches, syntax: Syntax) -> Result<(), Error> {
+    let path = matches.value_of("source").unwrap_or("-");
+    let source = Input {
+        file: Some(path.to_string()),
+    };
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+    let format = match (matches.value_of("format"), extension.as_deref()) {
+        (Some(format), _) => format,
+        (None, Some("txt")) => "todotxt",
+        (None, Some("json")) => "taskwarrior",
+        _ => "ics",
+    };
+    let todos: Vec<ImportedTask> = match format {
+        "todotxt" => todotxt::read_tasks(source.open()?)?
+            .strict()?
+            .into_iter()
+            .map(|task| ImportedTask {
+                task,
+                ..Default::default()
+            })
+            .collect(),
+        "taskwarrior" => taskwarrior::read_tasks(source.open()?)?,
+        _ => ics::read_todos(source.open()?)?,
+    };
+
+    let Some(target) = matches.value_of("into") else {
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        for todo in &todos {
+            write!(out, "{}", render_imported(todo, syntax))?;
+        }
+        return Ok(());
+    };
+
+    let existing = match fs::read_to_string(target) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(io::Error::new(err.kind(), format!("{}: {}", target, err)).into()),
+    };
+    let dedup = matches.is_present("dedup");
+    // Both the source's UID and the UIDs the task would be exported with from the
+    // target file identify duplicates
+    let exported_ids = |task: &Task| [ics::uid(task), taskwarrior::uuid(task)];
+    let mut known: HashSet<String> = HashSet::new();
+    if dedup {
+        let mut uids = UidAssigner::default();
+        for mut task in parse_input(&existing).tasks {
+            task.file = Some(target.to_string());
+            uids.assign(&mut task);
+            known.extend(exported_ids(&task));
         }
     }
+    // Imported tasks repeating a name are numbered among themselves, so importing the
+    // same tasks again finds each of them in the target
+    let mut imported_uids = UidAssigner::default();
+
+    let mut lines = String::new();
+    let mut imported = 0;
+    let mut skipped = 0;
+    for todo in todos {
+        let rendered = render_imported(&todo, syntax);
+        // Read the task back so its UIDs match the ones it will have in the file, even
+        // when rendering moved tags into its name
+        let mut task = parse_input(&rendered)
+            .tasks
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                Error::Parse(vec![Diagnostic {
+                    line: todo.task.line.unwrap_or(0),
+                    column: 1,
+                    message: format!(
+                        "imported task `{}` does not read back as a Markdown task",
+                        todo.task.name
+                    ),
+                }])
+            })?;
+        task.file = Some(target.to_string());
+        imported_uids.assign(&mut task);
+        let keys: Vec<String> = todo.uid.into_iter().chain(exported_ids(&task)).collect();
+        if dedup && keys.iter().any(|key| known.contains(key)) {
+            skipped += 1;
+            continue;
+        }
+        known.extend(keys);
+        lines.push_str(&rendered);
+        imported += 1;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(target)
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", target, err)))?;
+    if !existing.is_empty() && !existing.ends_with('\n') {
+        file.write_all(b"\n")?;
+    }
+    file.write_all(lines.as_bytes())?;
+
+    match skipped {
+        0 => eprintln!("Imported {} tasks into {}", imported, target),
+        _ => eprintln!(
+            "Imported {} tasks into {} ({} duplicates skipped)",
+            imported, target, skipped
+        ),
+    }
+    Ok(())
+}
+
+/// Rewrites each input with `rewrite`, given its text and file, printing the result or,
+/// with `--in-place`, saving it over the file.
+fn rewrite(
+    matches: &ArgMatches,
+    rewrite: impl Fn(&str, Option<&str>) -> String,
+) -> Result<(), Error> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for input in inputs(matches) {
+        let mut text = String::new();
+        input.open()?.read_to_string(&mut text)?;
+        let converted = rewrite(&text, input.file.as_deref());
+        match &input.file {
+            Some(path) if matches.is_present("in-place") => {
+                if converted != text {
+                    fs::write(path, converted)
+                        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path, err)))?;
+                }
+            }
+            _ => out.write_all(converted.as_bytes())?,
+        }
+    }
+    Ok(())
+}
+
+/// Renders an imported task as a Markdown task line, followed by its annotations as
+/// nested bullets.
+fn render_imported(todo: &ImportedTask, syntax: Syntax) -> String {
+    let mut text = markdown::render_task(&todo.task, syntax);
+    text.push('\n');
+    for annotation in &todo.annotations {
+        text.push_str(&format!("    - {}\n", annotation));
+    }
+    text
+}
+
+/// A source of tasks: a file named on the command line, or stdin when none are given.
+struct Input {
+    file: Option<String>,
+}
+
+impl Input {
+    fn open(&self) -> Result<Box<dyn BufRead>, Error> {
+        match &self.file {
+            Some(path) => {
+                let file = File::open(path)
+                    .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path, err)))?;
+                Ok(Box::new(BufReader::new(file)))
+            }
+            None => Ok(Box::new(io::stdin().lock())),
+        }
+    }
+
+    /// Records this input as the task's source, unless the task already names one, and
+    /// gives the task its stable identifier.
+    fn attribute(&self, mut task: Task, uids: &mut UidAssigner) -> Task {
+        if task.file.is_none() {
+            task.file = self.file.clone();
+        }
+        uids.assign(&mut task);
+        task
+    }
+}
+
+fn inputs(matches: &ArgMatches) -> Vec<Input> {
+    match matches.values_of("files") {
+        Some(files) => files
+            .map(|file| Input {
+                file: Some(file.to_string()),
+            })
+            .collect(),
+        None => vec![Input { file: None }],
+    }
+}
+
+fn files_arg() -> Arg<'static> {
+    Arg::with_name("files")
+        .value_name("FILE")
+        .help("Files to read tasks from. Reads stdin when none are given.")
+        .multiple_values(true)
 }
@@ -0,0 +1,312 @@
+//! Rendering tasks back into Obsidian Tasks Markdown.
+
+use crate::dates::format_duration;
+use crate::error::{Error, Result};
+use crate::parser::{
+    created_pattern, date_pattern, DATAVIEW_PATTERN, DEPENDS_ON_PATTERN, ESTIMATE_PATTERN,
+    ID_PATTERN, RECURRENCE_PATTERN,
+};
+use crate::{content_id, parse_input, Task};
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// How dates, priority and recurrence are written on a task line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Syntax {
+    /// Obsidian Tasks emoji markers, e.g. `⏫ 📅 2024-02-10`.
+    #[default]
+    Emoji,
+    /// Dataview inline fields, e.g. `[priority:: high] [due:: 2024-02-10]`.
+    Dataview,
+}
+
+impl fmt::Display for Syntax {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Syntax::Emoji => "emoji",
+            Syntax::Dataview => "dataview",
+        })
+    }
+}
+
+impl FromStr for Syntax {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "emoji" => Ok(Syntax::Emoji),
+            "dataview" => Ok(Syntax::Dataview),
+            other => Err(Error::Config(format!(
+                "unknown syntax `{}`: expected emoji or dataview",
+                other
+            ))),
+        }
+    }
+}
+
+/// Renders `task` as a Markdown task line, with markers in the order the Obsidian
+/// Tasks plugin writes them: ID, dependencies, priority, recurrence, start, scheduled,
+/// due, done, and finally the block ID. Its
+/// notes follow on the next lines, indented under the task.
+///
+/// Tags and contexts missing from the name, as when the task came from todo.txt, are
+/// appended to it as `#tag` and `@context`.
+pub fn render_task(task: &Task, syntax: Syntax) -> String {
+    let mut text = format!(
+        "- [{}] {}",
+        if task.completed { "x" } else { " " },
+        description(task, syntax)
+    );
+    for note in task.notes.iter().flat_map(|notes| notes.lines()) {
+        text.push('\n');
+        if !note.is_empty() {
+            text.push_str("  ");
+            text.push_str(note);
+        }
+    }
+    text
+}
+
+/// Rewrites the markers on every task line in `input` in `syntax`, in place, leaving
+/// the rest of the line, all other lines, including notes, the list indentation and
+/// the line endings untouched. Lines already written in `syntax` do not change, so
+/// converting twice is the same as converting once.
+///
+/// Lines with problems the parser reports, such as invalid dates, are left as they are
+/// so nothing is lost in the conversion.
+pub fn convert(input: &str, syntax: Syntax) -> String {
+    let (markers, to_field): (Regex, fn(&Captures) -> Option<Field>) = match syntax {
+        Syntax::Emoji => (dataview_regex(), |caps| Field::from_key(&caps[1])),
+        Syntax::Dataview => (emoji_regex(), |caps| Field::from_emoji(&caps[0])),
+    };
+    rewrite_task_lines(input, |line, task| {
+        let converted = markers.replace_all(line, |caps: &Captures| {
+            let text = &caps[0];
+            // Keep the spacing a recurrence rule runs on with
+            let trailing = &text[text.trim_end().len()..];
+            match to_field(caps).and_then(|field| field.render(task, syntax)) {
+                Some(marker) => format!("{}{}", marker, trailing),
+                None => text.to_string(),
+            }
+        });
+        match converted {
+            Cow::Owned(converted) => Some(converted),
+            Cow::Borrowed(_) => None,
+        }
+    })
+}
+
+/// Appends a block ID to every task line in `input` that has neither a 🆔 nor a block
+/// ID, leaving all other lines untouched. Each new ID is the `uid` the task is given
+/// when read from `file`, so it does not change once written; tasks repeating a name
+/// in the file get IDs of their own, numbered as [`crate::UidAssigner`] numbers them.
+///
+/// Lines with problems the parser reports are left as they are.
+pub fn assign_block_ids(input: &str, file: Option<&str>) -> String {
+    let mut used: HashSet<String> = parse_input(input)
+        .tasks
+        .into_iter()
+        .filter_map(|task| task.id.or(task.block_id))
+        .collect();
+    rewrite_task_lines(input, |line, task| {
+        if task.id.is_some() || task.block_id.is_some() {
+            return None;
+        }
+        let id = (0..)
+            .map(|occurrence| content_id(file, &task.name, occurrence))
+            .find(|id| !used.contains(id))?;
+        used.insert(id.clone());
+        Some(format!("{} ^{}", line.trim_end(), id))
+    })
+}
+
+/// Replaces each task line of `input` the parser finds no problems on with the result
+/// of `rewrite`, when it returns one, keeping every other line and all line endings.
+fn rewrite_task_lines(
+    input: &str,
+    mut rewrite: impl FnMut(&str, &Task) -> Option<String>,
+) -> String {
+    let parsed = parse_input(input);
+    let problems: HashSet<usize> = parsed.diagnostics.iter().map(|d| d.line).collect();
+    let tasks: HashMap<usize, &Task> = parsed
+        .tasks
+        .iter()
+        .filter_map(|task| Some((task.line?, task)))
+        .filter(|(line, _)| !problems.contains(line))
+        .collect();
+    let mut output = String::with_capacity(input.len());
+    for (index, raw) in input.split_inclusive('\n').enumerate() {
+        let line = raw.trim_end_matches(['\n', '\r']);
+        match tasks.get(&(index + 1)).and_then(|task| rewrite(line, task)) {
+            Some(rewritten) => {
+                output.push_str(&rewritten);
+                output.push_str(&raw[line.len()..]);
+            }
+            None => output.push_str(raw),
+        }
+    }
+    output
+}
+
+/// The part of a task line after its checkbox.
+fn description(task: &Task, syntax: Syntax) -> String {
+    let mut words: Vec<String> = task.name.split_whitespace().map(str::to_string).collect();
+    let missing: Vec<String> = task
+        .tags
+        .iter()
+        .map(|tag| format!("#{}", tag))
+        .chain(task.contexts.iter().map(|context| format!("@{}", context)))
+        .filter(|word| !words.contains(word))
+        .collect();
+    words.extend(missing);
+    words.extend(
+        Field::ALL
+            .iter()
+            .filter_map(|field| field.render(task, syntax)),
+    );
+    if let Some(block_id) = &task.block_id {
+        words.push(format!("^{}", block_id));
+    }
+    words.join(" ")
+}
+
+/// A task property written as a marker on the task line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Id,
+    DependsOn,
+    Priority,
+    Recurrence,
+    Estimate,
+    Created,
+    Start,
+    Scheduled,
+    Due,
+    Done,
+}
+
+impl Field {
+    /// Every field, in the order the Obsidian Tasks plugin writes them.
+    const ALL: [Field; 10] = [
+        Field::Id,
+        Field::DependsOn,
+        Field::Priority,
+        Field::Recurrence,
+        Field::Estimate,
+        Field::Created,
+        Field::Start,
+        Field::Scheduled,
+        Field::Due,
+        Field::Done,
+    ];
+
+    /// The field written with the emoji `marker` starts with.
+    fn from_emoji(marker: &str) -> Option<Field> {
+        let field = match marker.chars().next()? {
+            '🆔' => Field::Id,
+            '⛔' => Field::DependsOn,
+            '🔺' | '⏫' | '🔼' | '🔽' | '⏬' => Field::Priority,
+            '🔁' => Field::Recurrence,
+            '⏱' => Field::Estimate,
+            '➕' => Field::Created,
+            '🛫' => Field::Start,
+            '⏳' => Field::Scheduled,
+            '📅' => Field::Due,
+            '✅' => Field::Done,
+            _ => return None,
+        };
+        Some(field)
+    }
+
+    /// The field written as the Dataview inline field `key`.
+    fn from_key(key: &str) -> Option<Field> {
+        Field::ALL.into_iter().find(|field| field.key() == key)
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            Field::Id => "id",
+            Field::DependsOn => "dependsOn",
+            Field::Priority => "priority",
+            Field::Recurrence => "repeat",
+            Field::Estimate => "estimate",
+            Field::Created => "created",
+            Field::Start => "start",
+            Field::Scheduled => "scheduled",
+            Field::Due => "due",
+            Field::Done => "completion",
+        }
+    }
+
+    /// The marker giving `task` its value of this field in `syntax`, if it has one.
+    fn render(self, task: &Task, syntax: Syntax) -> Option<String> {
+        let (date, time) = match self {
+            Field::Created => (task.created, None),
+            Field::Start => (task.start, task.start_time),
+            Field::Scheduled => (task.scheduled, task.scheduled_time),
+            Field::Due => (task.due, task.due_time),
+            Field::Done => (task.done, task.done_time),
+            _ => (None, None),
+        };
+        let value = match self {
+            Field::Id => task.id.clone()?,
+            Field::DependsOn => Some(task.depends_on.join(",")).filter(|ids| !ids.is_empty())?,
+            Field::Priority if task.priority == Default::default() => return None,
+            Field::Priority if syntax == Syntax::Emoji => {
+                return Some(task.priority.emoji().to_string())
+            }
+            Field::Priority => task.priority.as_str().to_string(),
+            Field::Recurrence => task.recurrence.clone()?,
+            Field::Estimate => format_duration(task.estimate?),
+            _ => match time {
+                Some(time) => format!("{} {}", date?.format("%Y-%m-%d"), time.format("%H:%M")),
+                None => date?.format("%Y-%m-%d").to_string(),
+            },
+        };
+        Some(match syntax {
+            Syntax::Emoji => format!(
+                "{} {}",
+                match self {
+                    Field::Id => "🆔",
+                    Field::DependsOn => "⛔",
+                    Field::Recurrence => "🔁",
+                    Field::Estimate => "⏱",
+                    Field::Created => "➕",
+                    Field::Start => "🛫",
+                    Field::Scheduled => "⏳",
+                    Field::Due => "📅",
+                    _ => "✅",
+                },
+                value
+            ),
+            Syntax::Dataview => format!("[{}:: {}]", self.key(), value),
+        })
+    }
+}
+
+/// Matches the emoji markers the parser reads, with their values.
+fn emoji_regex() -> Regex {
+    Regex::new(
+        &[
+            date_pattern("[📅⏳🛫✅]"),
+            created_pattern("➕"),
+            "[🔺⏫🔼🔽⏬]".to_string(),
+            ESTIMATE_PATTERN.to_string(),
+            ID_PATTERN.to_string(),
+            DEPENDS_ON_PATTERN.to_string(),
+            RECURRENCE_PATTERN.to_string(),
+        ]
+        .join("|"),
+    )
+    .unwrap()
+}
+
+/// Matches the Dataview inline fields the parser reads, capturing their keys.
+fn dataview_regex() -> Regex {
+    Regex::new(DATAVIEW_PATTERN).unwrap()
+}
@@ -0,0 +1,69 @@
+//! `@mentions` in task names, naming the people a task is assigned to or the context
+//! it needs, as in `- [ ] Review PR @alice @bob` or `- [ ] Call the bank @phone`.
+//!
+//! Mentions become the task's contexts, the same field todo.txt `@context` words fill,
+//! and stay in the name unless [`strip`] removes them. A mention must start a word, so
+//! email addresses such as `bob@example.com` are not mentions.
+
+use crate::Task;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Compiled once, as every task's name is searched for mentions.
+fn mention_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"(?:^|\s)@(\w+(?:[.-]\w+)*)").unwrap())
+}
+
+/// Returns the mentions in `text` without their `@`, in order and without repeats,
+/// which are matched ignoring case.
+pub fn extract(text: &str) -> Vec<String> {
+    let mut mentions: Vec<String> = Vec::new();
+    for caps in mention_regex().captures_iter(text) {
+        if !mentions
+            .iter()
+            .any(|mention| mention.eq_ignore_ascii_case(&caps[1]))
+        {
+            mentions.push(caps[1].to_string());
+        }
+    }
+    mentions
+}
+
+/// Removes every mention from `text`.
+pub fn strip(text: &str) -> String {
+    mention_regex()
+        .replace_all(text, "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Groups `tasks` by the contexts they mention, in alphabetical order ignoring case,
+/// followed by the tasks mentioning none under `None`. A task mentioning several
+/// contexts appears in each of their groups.
+pub fn group<'a>(tasks: &[&'a Task]) -> Vec<(Option<String>, Vec<&'a Task>)> {
+    let mut groups: Vec<(Option<String>, Vec<&'a Task>)> = Vec::new();
+    let mut unassigned = Vec::new();
+    for &task in tasks {
+        if task.contexts.is_empty() {
+            unassigned.push(task);
+        }
+        for context in &task.contexts {
+            let found = groups.iter_mut().find(|(name, _)| {
+                name.as_deref()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(context))
+            });
+            match found {
+                Some((_, members)) if members.last().is_some_and(|&m| std::ptr::eq(m, task)) => {}
+                Some((_, members)) => members.push(task),
+                None => groups.push((Some(context.clone()), vec![task])),
+            }
+        }
+    }
+    groups.sort_by_key(|(name, _)| name.as_deref().map(str::to_lowercase));
+    if !unassigned.is_empty() {
+        groups.push((None, unassigned));
+    }
+    groups
+}
@@ -0,0 +1,216 @@
+//! Emacs Org-mode input and output.
+//!
+//! Headlines with a `TODO` or `DONE` keyword are tasks:
+//!
+//! ```org
+//! * TODO [#A] Write report                                              :work:
+//!   DEADLINE: <2024-02-10 Sat +1w> SCHEDULED: <2024-02-08 Thu>
+//! ```
+//!
+//! Priority cookies map `A`, `B` and `C` to high, medium and low (later letters count as
+//! lowest), headline tags become tags, and the `DEADLINE` and `SCHEDULED` timestamps
+//! set the due and scheduled dates, and their times of day, whether on the headline or
+//! on the planning line below it. A repeater such as `+1w` or `.+1w` becomes the
+//! recurrence rule. Org has no start date, so 🛫 is not written.
+
+use crate::error::Result;
+use crate::recurrence::{split_repeater, Interval};
+use crate::{Diagnostic, ParsedInput, Priority, Task};
+use chrono::{Local, NaiveDate, NaiveTime};
+use regex::Regex;
+use std::io::{Read, Write};
+
+/// Words that start a planning line under a headline.
+const PLANNING_KEYWORDS: [&str; 3] = ["DEADLINE:", "SCHEDULED:", "CLOSED:"];
+
+/// Parses Org text into tasks, one per `TODO` or `DONE` headline.
+pub fn parse(input: &str) -> ParsedInput {
+    // The keyword must be a word of its own, so `* TODOS for the week` is not a task
+    let headline_regex = Regex::new(
+        r"^\*+\s+(TODO|DONE)(?:\s+\[#([A-Z])\])?(?:\s+(.*?))??(?:\s+(:[^\s:]+(?::[^\s:]+)*:))?\s*$",
+    )
+    .unwrap();
+    let planning_regex = Regex::new(r"(DEADLINE|SCHEDULED):\s*<([^>]*)>").unwrap();
+    let today = Local::now().date_naive();
+
+    let lines: Vec<&str> = input.lines().collect();
+    let mut tasks = Vec::new();
+    let mut diagnostics = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let Some(caps) = headline_regex.captures(line) else {
+            continue;
+        };
+        let mut task = Task {
+            completed: &caps[1] == "DONE",
+            priority: caps
+                .get(2)
+                .map_or(Priority::None, |m| priority_from_cookie(m.as_str())),
+            tags: caps.get(4).map_or_else(Vec::new, |m| {
+                m.as_str()
+                    .split(':')
+                    .filter(|tag| !tag.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            }),
+            line: Some(index + 1),
+            ..Default::default()
+        };
+
+        // Planning may trail the headline or sit on the line right below it
+        let title_start = caps.get(3).map_or(line.len(), |m| m.start());
+        let mut title = caps.get(3).map_or("", |m| m.as_str()).to_string();
+        let mut planning = Vec::new();
+        if let Some(found) = planning_regex.find(&title) {
+            planning.push((index, *line, title_start + found.start()));
+            title.truncate(found.start());
+        }
+        if let Some(next) = lines.get(index + 1) {
+            let first_word = next.split_whitespace().next().unwrap_or("");
+            if PLANNING_KEYWORDS.contains(&first_word) {
+                planning.push((index + 1, *next, 0));
+            }
+        }
+        for (line_index, text, from) in planning {
+            for caps in planning_regex.captures_iter(&text[from..]) {
+                let stamp = caps.get(2).unwrap();
+                let Some((date, time, repeater)) = parse_timestamp(stamp.as_str()) else {
+                    diagnostics.push(Diagnostic {
+                        line: line_index + 1,
+                        column: text[..from + stamp.start()].chars().count() + 1,
+                        message: format!("invalid timestamp `<{}>`", stamp.as_str()),
+                    });
+                    continue;
+                };
+                if &caps[1] == "DEADLINE" {
+                    (task.due, task.due_time) = (Some(date), time);
+                } else {
+                    (task.scheduled, task.scheduled_time) = (Some(date), time);
+                }
+                if let Some(repeater) = repeater {
+                    task.recurrence = task.recurrence.or(Some(repeater));
+                }
+            }
+        }
+
+        task.name = title.trim().to_string();
+        task.overdue = !task.completed && task.due.is_some_and(|due| due < today);
+        tasks.push(task);
+    }
+    ParsedInput { tasks, diagnostics }
+}
+
+/// Reads all of `reader` and parses it as Org.
+pub fn read_tasks<R: Read>(mut reader: R) -> Result<ParsedInput> {
+    let mut input = String::new();
+    reader.read_to_string(&mut input)?;
+    Ok(parse(&input))
+}
+
+/// Writes `tasks` as top-level Org headlines.
+pub fn write_tasks<W: Write>(mut writer: W, tasks: &[&Task]) -> Result<()> {
+    for task in tasks {
+        writeln!(writer, "{}", render_task(task))?;
+    }
+    Ok(())
+}
+
+/// Renders `task` as an Org headline, followed by a planning line when it has dates.
+pub fn render_task(task: &Task) -> String {
+    let mut text = format!("* {}", if task.completed { "DONE" } else { "TODO" });
+    if let Some(cookie) = priority_cookie(task.priority) {
+        text.push_str(&format!(" [#{}]", cookie));
+    }
+    let name = task.name_without_tags();
+    if !name.is_empty() {
+        text.push(' ');
+        text.push_str(&name);
+    }
+    if !task.tags.is_empty() {
+        text.push_str(&format!(" :{}:", task.tags.join(":")));
+    }
+
+    // The repeater goes on the deadline, or on the scheduled date when there is none
+    let mut pending_repeater = task.recurrence.as_deref().and_then(repeater);
+    let mut planning = Vec::new();
+    let dates = [
+        ("DEADLINE", task.due, task.due_time),
+        ("SCHEDULED", task.scheduled, task.scheduled_time),
+    ];
+    for (keyword, date, time) in dates {
+        if let Some(date) = date {
+            let mut stamp = date.format("%Y-%m-%d %a").to_string();
+            if let Some(time) = time {
+                stamp.push_str(&time.format(" %H:%M").to_string());
+            }
+            if let Some(repeater) = pending_repeater.take() {
+                stamp.push(' ');
+                stamp.push_str(&repeater);
+            }
+            planning.push(format!("{}: <{}>", keyword, stamp));
+        }
+    }
+    if !planning.is_empty() {
+        text.push_str("\n  ");
+        text.push_str(&planning.join(" "));
+    }
+    text
+}
+
+/// Converts an Obsidian Tasks recurrence rule into an Org repeater such as `+2w`, or
+/// `.+2w` for rules that repeat from completion.
+pub fn repeater(recurrence: &str) -> Option<String> {
+    let interval = Interval::parse(recurrence)?;
+    let mark = if interval.when_done { ".+" } else { "+" };
+    Some(format!("{}{}", mark, interval.repeater()))
+}
+
+/// Parses the inside of an active timestamp, such as `2024-02-10 Sat 10:00 +1w`, into
+/// its date, its time of day (the start of a range such as `10:00-11:30`) and the
+/// recurrence rule of its repeater, if any.
+pub(crate) fn parse_timestamp(
+    stamp: &str,
+) -> Option<(NaiveDate, Option<NaiveTime>, Option<String>)> {
+    let mut parts = stamp.split_whitespace();
+    let date = NaiveDate::parse_from_str(parts.next()?, "%Y-%m-%d").ok()?;
+    let parts: Vec<&str> = parts.collect();
+    let time = parts.iter().find_map(|part| {
+        let start = part.split_once('-').map_or(*part, |(start, _)| start);
+        NaiveTime::parse_from_str(start, "%H:%M").ok()
+    });
+    // `+` and `++` repeat on schedule, and `.+` from completion
+    let recurrence = parts.iter().find_map(|part| {
+        let (marks, count, unit) = split_repeater(part)?;
+        let when_done = match marks {
+            ".+" => true,
+            "+" | "++" => false,
+            _ => return None,
+        };
+        Some(
+            Interval {
+                count,
+                unit,
+                when_done,
+            }
+            .to_string(),
+        )
+    });
+    Some((date, time, recurrence))
+}
+
+pub(crate) fn priority_from_cookie(cookie: &str) -> Priority {
+    match cookie {
+        "A" => Priority::High,
+        "B" => Priority::Medium,
+        "C" => Priority::Low,
+        _ => Priority::Lowest,
+    }
+}
+
+fn priority_cookie(priority: Priority) -> Option<char> {
+    match priority {
+        Priority::Highest | Priority::High => Some('A'),
+        Priority::Medium => Some('B'),
+        Priority::Low | Priority::Lowest => Some('C'),
+        Priority::None => None,
+    }
+}
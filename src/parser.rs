@@ -0,0 +1,884 @@
+//! Line-oriented parsing of Markdown task lists.
+//!
+//! [`Parser`] consumes one line at a time, so tasks can be streamed from a reader with
+//! [`TaskReader`] without holding the whole input in memory. A task is held back only
+//! until its list item ends, so the indented lines and nested bullets below it can be
+//! kept as its notes. [`parse_input`] and
+//! [`parse_reader`] are conveniences that collect everything at once.
+//!
+//! Only GitHub-flavoured task list items are tasks. The parser follows enough of the
+//! CommonMark block structure to tell them apart from look-alikes: list items may use
+//! `-`, `*`, `+`, `1.` or `1)` markers and sit inside blockquotes, while lines in YAML
+//! front matter, fenced or indented code blocks and HTML comments are skipped.
+
+use crate::dates::parse_duration;
+use crate::error::{Error, Result};
+use crate::{links, mentions, Priority, Task};
+use chrono::{Local, NaiveDate, NaiveTime};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+use std::ops::Range;
+
+/// Date markers recognised in task descriptions, paired with the field they set.
+const DATE_MARKERS: [(&str, &str); 5] = [
+    ("➕", "created"),
+    ("📅", "due"),
+    ("⏳", "scheduled"),
+    ("🛫", "start"),
+    ("✅", "done"),
+];
+
+/// An estimate, e.g. `⏱ 45m` or `⏱️ 1h30m`.
+pub(crate) const ESTIMATE_PATTERN: &str = r"⏱\x{FE0F}?\s*(\S*)";
+/// A recurrence rule, which runs up to the next marker, tag, mention, wikilink or block ID.
+pub(crate) const RECURRENCE_PATTERN: &str =
+    r"🔁\s*((?:[^📅⏳🛫✅➕⏱🔺⏫🔼🔽⏬🔁🆔⛔#@^\[]|\[[^\[])*)";
+/// Dependencies, e.g. `🆔 abc123` and `⛔ abc123,def456`.
+pub(crate) const ID_PATTERN: &str = r"🆔\s*([\w-]+)";
+pub(crate) const DEPENDS_ON_PATTERN: &str = r"⛔\s*([\w-]+(?:\s*,\s*[\w-]+)*)";
+/// Dataview inline fields, e.g. `[due:: 2024-02-10]`, in brackets or parentheses.
+pub(crate) const DATAVIEW_PATTERN: &str = r"[\[(](due|scheduled|start|completion|created|priority|repeat|estimate|id|dependsOn)::\s*([^\])]*?)\s*[\])]";
+
+/// A problem found in the input, located by its 1-based line and column.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// The result of parsing an input: the tasks found and any problems spotted along the way.
+pub struct ParsedInput {
+    pub tasks: Vec<Task>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ParsedInput {
+    /// Returns the parsed tasks, or an `Error::Parse` carrying every diagnostic if any were found.
+    pub fn strict(self) -> Result<Vec<Task>> {
+        if self.diagnostics.is_empty() {
+            Ok(self.tasks)
+        } else {
+            Err(Error::Parse(self.diagnostics))
+        }
+    }
+}
+
+/// Incremental Markdown task parser, fed one line at a time.
+pub struct Parser {
+    list_item_regex: Regex,
+    checkbox_regex: Regex,
+    due_date_regex: Regex,
+    scheduled_date_regex: Regex,
+    start_date_regex: Regex,
+    done_date_regex: Regex,
+    created_date_regex: Regex,
+    estimate_regex: Regex,
+    recurrence_regex: Regex,
+    id_regex: Regex,
+    depends_on_regex: Regex,
+    block_id_regex: Regex,
+    dataview_regex: Regex,
+    tag_regex: Regex,
+    line_number: usize,
+    block: BlockState,
+    /// Tasks read but not yet returned, in input order.
+    pending: Vec<Task>,
+    /// The list item and index in `pending` of each task whose item is still open.
+    open: Vec<(usize, usize)>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// The blocks left open by the lines seen so far, which decide how the next line is read.
+#[derive(Default)]
+struct BlockState {
+    /// Inside YAML front matter at the top of the input.
+    front_matter: bool,
+    /// The character and length of the fence opening the current code block.
+    fence: Option<(char, usize)>,
+    /// Inside an HTML comment that spans several lines.
+    comment: bool,
+    /// Content column and identifier of the enclosing list items, outermost first.
+    items: Vec<(usize, usize)>,
+    /// Identifier of the most recently opened list item.
+    next_item: usize,
+    /// Whether the previous line belongs to a paragraph, which indented code cannot interrupt.
+    paragraph: bool,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Parser {
+            list_item_regex: Regex::new(r"^([-*+]|\d{1,9}[.)])([ \t]+|$)").unwrap(),
+            checkbox_regex: Regex::new(r"^\[(.)\](?:[ \t]+|$)").unwrap(),
+            // Dates may be followed by a time of day, e.g. `⏳ 2024-02-10 14:00`
+            due_date_regex: date_regex("📅"),
+            scheduled_date_regex: date_regex("⏳"),
+            start_date_regex: date_regex("🛫"), // Regex for start dates
+            done_date_regex: date_regex("✅"),
+            created_date_regex: Regex::new(&created_pattern("➕")).unwrap(),
+            estimate_regex: Regex::new(ESTIMATE_PATTERN).unwrap(),
+            recurrence_regex: Regex::new(RECURRENCE_PATTERN).unwrap(),
+            id_regex: Regex::new(ID_PATTERN).unwrap(),
+            depends_on_regex: Regex::new(DEPENDS_ON_PATTERN).unwrap(),
+            // Obsidian block IDs end the line, e.g. `^abc123`
+            block_id_regex: Regex::new(r"(?:^|\s)\^([A-Za-z0-9-]+)\s*$").unwrap(),
+            dataview_regex: Regex::new(DATAVIEW_PATTERN).unwrap(),
+            // Obsidian tags need at least one non-digit, so `#1` is not a tag
+            tag_regex: Regex::new(r"(?:^|\s)#([\w/-]*[^\W\d][\w/-]*)").unwrap(),
+            line_number: 0,
+            block: BlockState::default(),
+            pending: Vec::new(),
+            open: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Diagnostics collected from the lines pushed so far.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+
+    /// Parses the next line of input, without its line terminator, returning the tasks
+    /// it completes. Tasks come out in input order once no enclosing task list item is
+    /// still open, since later lines may add to their notes. Problems are recorded as
+    /// diagnostics rather than returned.
+    pub fn push_line(&mut self, line: &str) -> Vec<Task> {
+        self.line_number += 1;
+        let item = self.task_item(line);
+        while let Some(&(id, _)) = self.open.last() {
+            if self.block.items.iter().any(|&(_, open)| open == id) {
+                break;
+            }
+            self.open.pop();
+        }
+        let ready = if self.open.is_empty() {
+            self.finish()
+        } else {
+            Vec::new()
+        };
+
+        let task =
+            item.and_then(|(status, description_start)| self.task(line, status, description_start));
+        match task {
+            Some(task) => {
+                let id = self.block.items.last().map_or(0, |&(_, id)| id);
+                self.open.push((id, self.pending.len()));
+                self.pending.push(task);
+            }
+            None => self.add_note(line),
+        }
+        ready
+    }
+
+    /// Returns the tasks still held back, as at the end of the input.
+    pub fn finish(&mut self) -> Vec<Task> {
+        self.open.clear();
+        self.pending
+            .drain(..)
+            .map(|mut task| {
+                task.notes = task
+                    .notes
+                    .map(|notes| notes.trim_matches('\n').to_string())
+                    .filter(|notes| !notes.is_empty());
+                task
+            })
+            .collect()
+    }
+
+    /// Adds `line` to the notes of the innermost task whose list item it belongs to,
+    /// without that item's indentation.
+    fn add_note(&mut self, line: &str) {
+        let Some(&(id, index)) = self.open.last() else {
+            return;
+        };
+        let column = self
+            .block
+            .items
+            .iter()
+            .find(|&&(_, open)| open == id)
+            .map_or(0, |&(column, _)| column);
+        let content = &line[blockquote_end(line)..];
+        let note = if content.trim().is_empty() {
+            ""
+        } else {
+            strip_indentation(content, column)
+        };
+        let notes = self.pending[index].notes.get_or_insert_with(String::new);
+        if !notes.is_empty() || !note.is_empty() {
+            notes.push('\n');
+        }
+        notes.push_str(note);
+    }
+
+    /// Builds the task on the task list item `line`, whose status symbol is at `status`.
+    fn task(&mut self, line: &str, status: Range<usize>, description_start: usize) -> Option<Task> {
+        let line_number = self.line_number;
+
+        // Obsidian's in-progress `/` status is listed as an open task, as are unknown
+        // symbols, which are reported too. Cancelled `-` tasks are closed, like done ones
+        let symbol = &line[status.clone()];
+        if !matches!(symbol, "x" | "X" | "/" | "-") && !symbol.trim().is_empty() {
+            self.diagnostics.push(Diagnostic {
+                line: line_number,
+                column: column_of(line, status.start),
+                message: format!("unknown status symbol `{}`", symbol),
+            });
+        }
+        self.diagnostics
+            .extend(check_date_markers(line_number, line));
+
+        let completed = matches!(symbol, "x" | "X" | "-");
+        let fields = self.dataview_fields(line_number, line, description_start);
+        let mut name_with_potential_dates = self
+            .dataview_regex
+            .replace_all(&line[description_start..], "")
+            .to_string();
+
+        // Take the block ID off first, so it is not read as part of a recurrence rule
+        let block_id = self
+            .block_id_regex
+            .captures(&name_with_potential_dates)
+            .map(|caps| caps[1].to_string());
+        name_with_potential_dates = self
+            .block_id_regex
+            .replace(&name_with_potential_dates, "")
+            .to_string();
+
+        // Extract and parse the due date
+        let (due, due_time) = split_date_time(
+            fields
+                .due
+                .or_else(|| parse_date(&self.due_date_regex, &name_with_potential_dates)),
+        );
+        // Extract and parse the scheduled date
+        let (scheduled, scheduled_time) = split_date_time(
+            fields
+                .scheduled
+                .or_else(|| parse_date(&self.scheduled_date_regex, &name_with_potential_dates)),
+        );
+        // Extract and parse the start date
+        let (start, start_time) = split_date_time(
+            fields
+                .start
+                .or_else(|| parse_date(&self.start_date_regex, &name_with_potential_dates)),
+        );
+        // Extract and parse the completion date
+        let (done, done_time) = split_date_time(
+            fields
+                .done
+                .or_else(|| parse_date(&self.done_date_regex, &name_with_potential_dates)),
+        );
+
+        // Extract and parse the creation date
+        let (created, _) = split_date_time(
+            fields
+                .created
+                .or_else(|| parse_date(&self.created_date_regex, &name_with_potential_dates)),
+        );
+
+        // Extract the estimate, e.g. "1h30m"
+        let mut estimate = fields.estimate;
+        if let Some(caps) = self.estimate_regex.captures(&line[description_start..]) {
+            let value = caps.get(1).unwrap();
+            match parse_duration(value.as_str()) {
+                Some(minutes) => estimate = estimate.or(Some(minutes)),
+                None => self.diagnostics.push(Diagnostic {
+                    line: line_number,
+                    column: column_of(line, description_start + caps.get(0).unwrap().start()),
+                    message: if value.as_str().is_empty() {
+                        "missing estimate after ⏱".to_string()
+                    } else {
+                        format!("invalid estimate `{}`", value.as_str())
+                    },
+                }),
+            }
+        }
+
+        // Extract the recurrence rule, e.g. "every week"
+        let recurrence = fields.recurrence.or_else(|| {
+            self.recurrence_regex
+                .captures(&name_with_potential_dates)
+                .map(|caps| caps[1].trim().to_string())
+                .filter(|rule| !rule.is_empty())
+        });
+
+        // Extract the dependency markers
+        let id = fields.id.or_else(|| {
+            self.id_regex
+                .captures(&name_with_potential_dates)
+                .map(|caps| caps[1].to_string())
+        });
+        let depends_on = fields.depends_on.unwrap_or_else(|| {
+            self.depends_on_regex
+                .captures(&name_with_potential_dates)
+                .map_or_else(Vec::new, |caps| split_ids(&caps[1]))
+        });
+
+        // Tags stay in the name, as Obsidian shows them inline
+        let tags = self
+            .tag_regex
+            .captures_iter(&name_with_potential_dates)
+            .map(|caps| caps[1].to_string())
+            .collect();
+
+        // Clean the task name by removing date strings, the recurrence rule and dependencies
+        name_with_potential_dates = remove_date_strings(
+            &[
+                &self.due_date_regex,
+                &self.scheduled_date_regex,
+                &self.start_date_regex,
+                &self.done_date_regex,
+                &self.created_date_regex,
+                &self.estimate_regex,
+                &self.recurrence_regex,
+                &self.id_regex,
+                &self.depends_on_regex,
+            ],
+            name_with_potential_dates,
+        );
+
+        // A task due at a time of day is overdue once that time has passed
+        let now = Local::now().naive_local();
+        let overdue = due.is_some_and(|due_date| match due_time {
+            Some(time) => due_date.and_time(time) < now,
+            None => due_date < now.date(),
+        });
+
+        let (description_without_priorities, emoji_priority) =
+            parse_priority(&name_with_potential_dates);
+        let priority = fields.priority.unwrap_or(emoji_priority);
+
+        // Clean up the remaining description
+        let cleaned_description = clean_description(&description_without_priorities);
+        // Mentions, like tags, stay in the name
+        let contexts = mentions::extract(&cleaned_description);
+
+        Some(Task {
+            links: links::extract(&cleaned_description),
+            name: cleaned_description,
+            completed,
+            created,
+            due,
+            due_time,
+            scheduled,
+            scheduled_time,
+            start,
+            start_time,
+            done,
+            done_time,
+            overdue,
+            not_started: false,
+            due_today: false,
+            due_soon: false,
+            priority,
+            recurrence,
+            estimate,
+            urgency: 0.0,
+            id,
+            depends_on,
+            blocked: false,
+            block_id,
+            uid: None,
+            tags,
+            contexts,
+            notes: None,
+            file: None,
+            line: Some(line_number),
+            parent: None,
+        })
+    }
+
+    /// Follows the block structure through `line` and, when it starts a task list item,
+    /// returns the byte range of its status symbol and the offset of its description.
+    fn task_item(&mut self, line: &str) -> Option<(Range<usize>, usize)> {
+        let block = &mut self.block;
+        if self.line_number == 1 && line.trim_end() == "---" {
+            block.front_matter = true;
+            return None;
+        }
+        if block.front_matter {
+            block.front_matter = !matches!(line.trim_end(), "---" | "...");
+            return None;
+        }
+
+        let content_start = blockquote_end(line);
+        let content = &line[content_start..];
+        if let Some((fence, length)) = block.fence {
+            let closing = content.trim();
+            if closing.len() >= length && closing.chars().all(|c| c == fence) {
+                block.fence = None;
+            }
+            return None;
+        }
+        if block.comment {
+            block.comment = !content.contains("-->");
+            return None;
+        }
+        if content.trim().is_empty() {
+            block.paragraph = false;
+            return None;
+        }
+
+        let indent = indentation(content);
+        let text = content.trim_start();
+        let text_start = line.len() - text.len();
+        let item = self
+            .list_item_regex
+            .captures(text)
+            .filter(|_| !is_thematic_break(text));
+        let fence = text
+            .chars()
+            .next()
+            .filter(|c| matches!(c, '`' | '~'))
+            .map(|fence| (fence, text.chars().take_while(|&c| c == fence).count()))
+            .filter(|&(fence, length)| {
+                length >= 3 && !(fence == '`' && text[length..].contains('`'))
+            });
+        let starts_block = item.is_some()
+            || fence.is_some()
+            || text.starts_with("<!--")
+            || is_thematic_break(text)
+            || is_heading(text);
+
+        // A paragraph continues through less indented lines, unless they start a new block
+        if starts_block || !block.paragraph {
+            while block
+                .items
+                .last()
+                .is_some_and(|&(column, _)| column > indent)
+            {
+                block.items.pop();
+            }
+        }
+        if indent >= block.items.last().map_or(0, |&(column, _)| column) + 4 {
+            // Indented code, or the continuation of a paragraph
+            return None;
+        }
+
+        if let Some(fence) = fence {
+            block.fence = Some(fence);
+            block.paragraph = false;
+            return None;
+        }
+        if let Some(comment) = text.strip_prefix("<!--") {
+            block.comment = !comment.contains("-->");
+            block.paragraph = false;
+            return None;
+        }
+        if is_thematic_break(text) || is_heading(text) {
+            block.paragraph = false;
+            return None;
+        }
+
+        block.paragraph = true;
+        let caps = item?;
+        let marker = caps.get(1).unwrap();
+        let spacing = caps.get(2).unwrap();
+        let padding = match indentation(spacing.as_str()) {
+            width if width > 4 || spacing.end() == text.len() => 1,
+            width => width,
+        };
+        block.next_item += 1;
+        block.items.push((
+            indent + marker.as_str().chars().count() + padding,
+            block.next_item,
+        ));
+
+        let checkbox = self.checkbox_regex.captures(&text[spacing.end()..])?;
+        let offset = text_start + spacing.end();
+        let status = checkbox.get(1).unwrap();
+        Some((
+            offset + status.start()..offset + status.end(),
+            offset + checkbox.get(0).unwrap().end(),
+        ))
+    }
+
+    /// Reads the Dataview inline fields in the task description starting at byte
+    /// `offset` of `line`, reporting values that cannot be read.
+    fn dataview_fields(&mut self, line_number: usize, line: &str, offset: usize) -> DataviewFields {
+        let mut fields = DataviewFields::default();
+        for caps in self.dataview_regex.captures_iter(&line[offset..]) {
+            let key = &caps[1];
+            let value = &caps[2];
+            let column = column_of(line, offset + caps.get(0).unwrap().start());
+            let problem = match key {
+                "priority" => match Priority::from_name(value) {
+                    Some(priority) => {
+                        fields.priority = Some(priority);
+                        None
+                    }
+                    None => Some(format!("unknown priority `{}`", value)),
+                },
+                "repeat" => {
+                    fields.recurrence = Some(value.to_string()).filter(|rule| !rule.is_empty());
+                    None
+                }
+                "id" => {
+                    fields.id = Some(value.to_string()).filter(|id| !id.is_empty());
+                    None
+                }
+                "dependsOn" => {
+                    fields.depends_on = Some(split_ids(value));
+                    None
+                }
+                "estimate" => match parse_duration(value) {
+                    Some(minutes) => {
+                        fields.estimate = Some(minutes);
+                        None
+                    }
+                    None => Some(format!("invalid estimate `{}`", value)),
+                },
+                _ if value.is_empty() => Some(format!("missing {} date in [{}::]", key, key)),
+                _ => match parse_date_time(value) {
+                    Some(date) => {
+                        let field = match key {
+                            "due" => &mut fields.due,
+                            "scheduled" => &mut fields.scheduled,
+                            "start" => &mut fields.start,
+                            "created" => &mut fields.created,
+                            _ => &mut fields.done,
+                        };
+                        *field = Some(date);
+                        None
+                    }
+                    None => Some(format!("invalid {} date `{}`", key, value)),
+                },
+            };
+            if let Some(message) = problem {
+                self.diagnostics.push(Diagnostic {
+                    line: line_number,
+                    column,
+                    message,
+                });
+            }
+        }
+        fields
+    }
+}
+
+/// A date with an optional time of day.
+type DateTime = (NaiveDate, Option<NaiveTime>);
+
+/// Values given as Dataview inline fields, which take precedence over emoji markers.
+#[derive(Default)]
+struct DataviewFields {
+    due: Option<DateTime>,
+    scheduled: Option<DateTime>,
+    start: Option<DateTime>,
+    done: Option<DateTime>,
+    created: Option<DateTime>,
+    priority: Option<Priority>,
+    recurrence: Option<String>,
+    estimate: Option<u32>,
+    id: Option<String>,
+    depends_on: Option<Vec<String>>,
+}
+
+/// Streams tasks from a buffered reader, parsing one line at a time.
+///
+/// Diagnostics are accumulated and can be inspected once the iterator is exhausted.
+pub struct TaskReader<R> {
+    reader: R,
+    parser: Parser,
+    buffer: String,
+    ready: VecDeque<Task>,
+    finished: bool,
+}
+
+impl<R: BufRead> TaskReader<R> {
+    pub fn new(reader: R) -> Self {
+        TaskReader {
+            reader,
+            parser: Parser::new(),
+            buffer: String::new(),
+            ready: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    /// Diagnostics collected from the lines read so far.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        self.parser.diagnostics()
+    }
+
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        self.parser.into_diagnostics()
+    }
+}
+
+impl<R: BufRead> Iterator for TaskReader<R> {
+    type Item = Result<Task>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(task) = self.ready.pop_front() {
+                return Some(Ok(task));
+            }
+            if self.finished {
+                return None;
+            }
+            self.buffer.clear();
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => {
+                    self.finished = true;
+                    self.ready.extend(self.parser.finish());
+                }
+                Ok(_) => {
+                    let line = self.buffer.trim_end_matches(['\n', '\r']);
+                    self.ready.extend(self.parser.push_line(line));
+                }
+                Err(err) => return Some(Err(err.into())),
+            }
+        }
+    }
+}
+
+/// Reads all of `reader` and parses it, as [`parse_input`] does for a string.
+pub fn parse_reader<R: Read>(reader: R) -> Result<ParsedInput> {
+    let mut tasks = TaskReader::new(BufReader::new(reader));
+    let collected = tasks.by_ref().collect::<Result<Vec<Task>>>()?;
+    Ok(ParsedInput {
+        tasks: collected,
+        diagnostics: tasks.into_diagnostics(),
+    })
+}
+
+/// Parses the input text into `Task` objects, reporting malformed lines as diagnostics.
+pub fn parse_input(input: &str) -> ParsedInput {
+    let mut parser = Parser::new();
+    let mut tasks: Vec<Task> = input
+        .lines()
+        .flat_map(|line| parser.push_line(line))
+        .collect();
+    tasks.extend(parser.finish());
+    ParsedInput {
+        tasks,
+        diagnostics: parser.into_diagnostics(),
+    }
+}
+
+fn clean_description(description: &str) -> String {
+    let re = Regex::new(r"\s+").unwrap(); // Matches one or more whitespace characters
+    re.replace_all(description.trim(), " ").to_string()
+}
+
+pub fn parse_priority(description: &str) -> (String, Priority) {
+    let (priority, signifier) = if description.contains("🔺") {
+        (Priority::Highest, "🔺")
+    } else if description.contains("⏫") {
+        (Priority::High, "⏫")
+    } else if description.contains("🔼") {
+        (Priority::Medium, "🔼")
+    } else if description.contains("🔽") {
+        (Priority::Low, "🔽")
+    } else if description.contains("⏬") {
+        (Priority::Lowest, "⏬")
+    } else {
+        (Priority::None, "")
+    };
+
+    // Remove the signifier from the description to clean it up
+    let clean_description = if !signifier.is_empty() {
+        description.replace(signifier, "").trim().to_string()
+    } else {
+        description.to_string()
+    };
+
+    (clean_description, priority)
+}
+
+/// Reports date markers that are repeated or not followed by a valid `YYYY-MM-DD` date.
+fn check_date_markers(line_number: usize, line: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for (marker, field) in DATE_MARKERS {
+        for (occurrence, (offset, _)) in line.match_indices(marker).enumerate() {
+            let column = column_of(line, offset);
+            if occurrence > 0 {
+                diagnostics.push(Diagnostic {
+                    line: line_number,
+                    column,
+                    message: format!("duplicate {} date marker {}", field, marker),
+                });
+            }
+            let mut words = line[offset + marker.len()..].split_whitespace();
+            let value = words.next().unwrap_or("");
+            let time = words.next().filter(|word| is_time_like(word));
+            if let Some(time) = time.filter(|time| parse_time(time).is_none()) {
+                diagnostics.push(Diagnostic {
+                    line: line_number,
+                    column,
+                    message: format!("invalid {} time `{}`", field, time),
+                });
+            }
+            if NaiveDate::parse_from_str(value, "%Y-%m-%d").is_err() {
+                let message = if value.is_empty() {
+                    format!("missing {} date after {}", field, marker)
+                } else {
+                    format!("invalid {} date `{}`", field, value)
+                };
+                diagnostics.push(Diagnostic {
+                    line: line_number,
+                    column,
+                    message,
+                });
+            }
+        }
+    }
+    diagnostics.sort_by_key(|d| d.column);
+    diagnostics
+}
+
+/// Whether `word` is written like a time of day, `14:00`, whether or not it is valid.
+fn is_time_like(word: &str) -> bool {
+    word.split_once(':').is_some_and(|(hours, minutes)| {
+        (1..=2).contains(&hours.len())
+            && minutes.len() == 2
+            && hours
+                .chars()
+                .chain(minutes.chars())
+                .all(|c| c.is_ascii_digit())
+    })
+}
+
+/// Returns the byte offset where `line` continues after any blockquote markers.
+fn blockquote_end(line: &str) -> usize {
+    let mut end = 0;
+    loop {
+        let rest = &line[end..];
+        let trimmed = rest.trim_start_matches(' ');
+        if rest.len() - trimmed.len() > 3 {
+            return end;
+        }
+        let Some(quoted) = trimmed.strip_prefix('>') else {
+            return end;
+        };
+        end = line.len() - quoted.strip_prefix([' ', '\t']).unwrap_or(quoted).len();
+    }
+}
+
+/// Removes up to `width` columns of leading whitespace from `text`.
+fn strip_indentation(text: &str, width: usize) -> &str {
+    let mut column = 0;
+    for (offset, c) in text.char_indices() {
+        if column >= width {
+            return &text[offset..];
+        }
+        match c {
+            ' ' => column += 1,
+            '\t' => column += 4 - column % 4,
+            _ => return &text[offset..],
+        }
+    }
+    ""
+}
+
+/// Width of the leading whitespace of `text`, with tabs stopping at multiples of four.
+fn indentation(text: &str) -> usize {
+    let mut width = 0;
+    for c in text.chars() {
+        match c {
+            ' ' => width += 1,
+            '\t' => width += 4 - width % 4,
+            _ => break,
+        }
+    }
+    width
+}
+
+/// Whether `text` is a thematic break such as `***` or `- - -`.
+fn is_thematic_break(text: &str) -> bool {
+    let mut marks = text.chars().filter(|c| !c.is_whitespace());
+    let Some(first) = marks.next().filter(|c| matches!(c, '-' | '*' | '_')) else {
+        return false;
+    };
+    let rest: Vec<char> = marks.collect();
+    rest.len() >= 2 && rest.iter().all(|&c| c == first)
+}
+
+/// Whether `text` is an ATX heading such as `## Tasks`.
+fn is_heading(text: &str) -> bool {
+    let level = text.chars().take_while(|&c| c == '#').count();
+    (1..=6).contains(&level) && text[level..].chars().next().is_none_or(char::is_whitespace)
+}
+
+/// Converts a byte offset within `line` into a 1-based character column.
+fn column_of(line: &str, offset: usize) -> usize {
+    line[..offset].chars().count() + 1
+}
+
+fn parse_date(date_regex: &Regex, text: &str) -> Option<DateTime> {
+    let caps = date_regex.captures(text)?;
+    let date = NaiveDate::parse_from_str(&caps[1], "%Y-%m-%d").ok()?;
+    Some((date, caps.get(2).and_then(|m| parse_time(m.as_str()))))
+}
+
+/// Reads a Dataview date, `2024-02-10` or `2024-02-10 14:00`.
+fn parse_date_time(value: &str) -> Option<DateTime> {
+    let (date, time) = match value.split_once(' ') {
+        Some((date, time)) => (date, Some(parse_time(time.trim())?)),
+        None => (value, None),
+    };
+    Some((NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?, time))
+}
+
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M").ok()
+}
+
+fn split_date_time(date: Option<DateTime>) -> (Option<NaiveDate>, Option<NaiveTime>) {
+    date.map_or((None, None), |(date, time)| (Some(date), time))
+}
+
+/// Matches `marker` followed by a date and an optional time of day.
+fn date_regex(marker: &str) -> Regex {
+    Regex::new(&date_pattern(marker)).unwrap()
+}
+
+/// A pattern for `marker` followed by a date and an optional time of day, capturing both.
+pub(crate) fn date_pattern(marker: &str) -> String {
+    format!(
+        r"{} (\d{{4}}-\d{{2}}-\d{{2}})(?: (\d{{1,2}}:\d{{2}})\b)?",
+        marker
+    )
+}
+
+/// A pattern for `marker` followed by a date without a time, capturing the date.
+pub(crate) fn created_pattern(marker: &str) -> String {
+    format!(r"{} (\d{{4}}-\d{{2}}-\d{{2}})", marker)
+}
+
+/// Splits a comma-separated list of task IDs.
+fn split_ids(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn remove_date_strings(regexes: &[&Regex], mut text: String) -> String {
+    for regex in regexes {
+        text = regex.replace_all(&text, "").to_string();
+    }
+    text
+}
@@ -0,0 +1,122 @@
+//! Interval recurrence rules, shared by the formats that can carry them.
+//!
+//! Obsidian Tasks words a rule as `every week`, `every 2 days` or `every month when
+//! done`, where `when done` counts the next occurrence from completion rather than
+//! from the due date. Other tools write the same intervals as a count and a unit
+//! letter, such as todo.txt's `rec:+2d` and Org's `.+1w` repeaters, which
+//! [`split_repeater`] and [`Interval::repeater`] read and write. Rules naming weekdays
+//! or other schedules are not intervals and are left to the formats that support them.
+
+use regex::Regex;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// The unit an interval counts in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl Unit {
+    /// The unit's name, as in `every day`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Unit::Day => "day",
+            Unit::Week => "week",
+            Unit::Month => "month",
+            Unit::Year => "year",
+        }
+    }
+
+    /// The letter repeaters write the unit as, `d`, `w`, `m` or `y`.
+    pub fn letter(self) -> char {
+        match self {
+            Unit::Day => 'd',
+            Unit::Week => 'w',
+            Unit::Month => 'm',
+            Unit::Year => 'y',
+        }
+    }
+
+    pub fn from_letter(letter: char) -> Option<Unit> {
+        match letter {
+            'd' => Some(Unit::Day),
+            'w' => Some(Unit::Week),
+            'm' => Some(Unit::Month),
+            'y' => Some(Unit::Year),
+            _ => None,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Unit> {
+        [Unit::Day, Unit::Week, Unit::Month, Unit::Year]
+            .into_iter()
+            .find(|unit| unit.name() == name)
+    }
+}
+
+/// A rule repeating every `count` units, from completion when `when_done` is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interval {
+    pub count: u32,
+    pub unit: Unit,
+    pub when_done: bool,
+}
+
+impl Interval {
+    /// Reads a rule such as `every 2 weeks` or `every day when done`, ignoring case.
+    pub fn parse(rule: &str) -> Option<Interval> {
+        let rule = rule.trim().to_lowercase();
+        let (rule, when_done) = match rule.strip_suffix("when done") {
+            Some(rule) => (rule.trim(), true),
+            None => (rule.as_str(), false),
+        };
+        let caps = interval_regex().captures(rule)?;
+        let count = match caps.get(1) {
+            Some(count) => count.as_str().parse().ok()?,
+            None => 1,
+        };
+        Some(Interval {
+            count,
+            unit: Unit::from_name(&caps[2])?,
+            when_done,
+        })
+    }
+
+    /// The count and unit letter, as in `2w`, without the marks formats put before it.
+    pub fn repeater(&self) -> String {
+        format!("{}{}", self.count, self.unit.letter())
+    }
+}
+
+impl fmt::Display for Interval {
+    /// Writes the rule in Obsidian Tasks wording, e.g. `every 2 weeks when done`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.count {
+            1 => write!(f, "every {}", self.unit.name())?,
+            count => write!(f, "every {} {}s", count, self.unit.name())?,
+        }
+        if self.when_done {
+            write!(f, " when done")?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits a repeater such as `+1w`, `.+2d` or `3m` into the marks before its count,
+/// the count and the unit. What the marks mean is up to each format.
+pub fn split_repeater(value: &str) -> Option<(&str, u32, Unit)> {
+    let count_start = value.find(|c: char| c.is_ascii_digit())?;
+    let (marks, rest) = value.split_at(count_start);
+    let mut chars = rest.chars();
+    let unit = Unit::from_letter(chars.next_back()?)?;
+    Some((marks, chars.as_str().parse().ok()?, unit))
+}
+
+fn interval_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"^every\s+(?:(\d+)\s+)?(day|week|month|year)s?$").unwrap())
+}
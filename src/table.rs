@@ -0,0 +1,129 @@
+//! Aligned, human-readable table output for the terminal.
+//!
+//! Dates are shown relative to today ("in 3 days", "2 days overdue") and the name
+//! column is truncated so each row fits within the terminal width, when one is known.
+
+use crate::color::Theme;
+use crate::dates::{describe_due, describe_relative};
+use crate::error::Result;
+use crate::Task;
+use chrono::NaiveDate;
+use std::io::Write;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const HEADERS: [&str; 7] = [
+    "STATUS",
+    "PRIORITY",
+    "DUE",
+    "SCHEDULED",
+    "START",
+    "NAME",
+    "SOURCE",
+];
+const NAME_COLUMN: usize = 5;
+/// The name column is never truncated below this many cells.
+const MIN_NAME_WIDTH: usize = 10;
+const SEPARATOR: &str = "  ";
+
+/// Returns the width of the terminal attached to stdout, if there is one.
+pub fn terminal_width() -> Option<usize> {
+    terminal_size::terminal_size().map(|(terminal_size::Width(width), _)| width as usize)
+}
+
+/// Renders a task as the cells of one table row, in [`HEADERS`] order.
+fn row(task: &Task, today: NaiveDate) -> [String; 7] {
+    let relative = |date: &Option<NaiveDate>| {
+        date.map(|d| describe_relative(d, today))
+            .unwrap_or_default()
+    };
+    let source = match (&task.file, task.line) {
+        (Some(file), Some(line)) => format!("{}:{}", file, line),
+        (Some(file), None) => file.clone(),
+        (None, Some(line)) => format!("line {}", line),
+        (None, None) => String::new(),
+    };
+    [
+        if task.completed { "[x]" } else { "[ ]" }.to_string(),
+        task.priority.as_str().to_string(),
+        task.due.map(|d| describe_due(d, today)).unwrap_or_default(),
+        relative(&task.scheduled),
+        relative(&task.start),
+        task.name.clone(),
+        source,
+    ]
+}
+
+/// Writes `tasks` as an aligned table with a header row.
+///
+/// When `max_width` is given, the name column is truncated with an ellipsis so that
+/// rows fit; otherwise names are shown in full. Rows are coloured with `theme`, if any.
+pub fn write_tasks<W: Write>(
+    mut writer: W,
+    tasks: &[&Task],
+    today: NaiveDate,
+    max_width: Option<usize>,
+    theme: Option<&Theme>,
+) -> Result<()> {
+    let rows: Vec<[String; 7]> = tasks.iter().map(|task| row(task, today)).collect();
+
+    let mut widths = HEADERS.map(UnicodeWidthStr::width);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.width());
+        }
+    }
+    if let Some(max_width) = max_width {
+        let others: usize = widths
+            .iter()
+            .enumerate()
+            .filter(|&(column, _)| column != NAME_COLUMN)
+            .map(|(_, width)| width)
+            .sum::<usize>()
+            + SEPARATOR.len() * (HEADERS.len() - 1);
+        let available = max_width.saturating_sub(others).max(MIN_NAME_WIDTH);
+        widths[NAME_COLUMN] = widths[NAME_COLUMN].min(available);
+    }
+
+    let render = |cells: &[String; 7]| {
+        let line: Vec<String> = cells
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| pad(&truncate(cell, width), width))
+            .collect();
+        line.join(SEPARATOR).trim_end().to_string()
+    };
+
+    writeln!(writer, "{}", render(&HEADERS.map(str::to_string)))?;
+    for (task, cells) in tasks.iter().zip(&rows) {
+        let line = render(cells);
+        match theme {
+            Some(theme) => writeln!(writer, "{}", theme.paint(task, &line))?,
+            None => writeln!(writer, "{}", line)?,
+        }
+    }
+    Ok(())
+}
+
+/// Shortens `text` to at most `width` terminal cells, ending it with an ellipsis if cut.
+fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut truncated = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width + 1 > width {
+            break;
+        }
+        truncated.push(c);
+        used += char_width;
+    }
+    truncated.push('…');
+    truncated
+}
+
+/// Pads `text` with spaces to `width` terminal cells.
+fn pad(text: &str, width: usize) -> String {
+    format!("{}{}", text, " ".repeat(width.saturating_sub(text.width())))
+}
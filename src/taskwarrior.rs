@@ -0,0 +1,324 @@
+//! Conversion to and from Taskwarrior's `task export` JSON.
+//!
+//! Descriptions, status, the due and scheduled dates, priority (`H`, `M`, `L`), tags
+//! and simple recurrence rules map directly. Taskwarrior's `wait` date, which hides a
+//! task until then, stands in for the start date (🛫), `end` for the done date (✅) and
+//! `entry` for the created date (➕).
+//! Times of day carry over both ways, and a Taskwarrior date at local midnight is read
+//! as a whole day.
+//! Annotations are kept on import so they can be written as notes under the task, and
+//! each line of a task's notes is exported as an annotation. Exported tasks get a UUID
+//! derived from their `uid`, so re-exporting updates tasks instead of duplicating them.
+
+use crate::error::{Error, Result};
+use crate::recurrence::{Interval, Unit};
+use crate::{fnv1a, ImportedTask, Priority, Task};
+use chrono::{Local, NaiveDate, NaiveTime};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    #[default]
+    Pending,
+    Completed,
+    Deleted,
+    Waiting,
+    Recurring,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Annotation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    entry: Option<String>,
+    description: String,
+}
+
+/// A task as Taskwarrior exports it. Attributes marktask has no use for are ignored.
+#[derive(Debug, Serialize, Deserialize)]
+struct Record {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uuid: Option<String>,
+    description: String,
+    #[serde(default)]
+    status: Status,
+    /// When the task was created.
+    #[serde(default, with = "tw_date", skip_serializing_if = "Option::is_none")]
+    entry: Option<DateTime>,
+    #[serde(default, with = "tw_date", skip_serializing_if = "Option::is_none")]
+    due: Option<DateTime>,
+    #[serde(default, with = "tw_date", skip_serializing_if = "Option::is_none")]
+    scheduled: Option<DateTime>,
+    #[serde(default, with = "tw_date", skip_serializing_if = "Option::is_none")]
+    wait: Option<DateTime>,
+    /// When the task was completed.
+    #[serde(default, with = "tw_date", skip_serializing_if = "Option::is_none")]
+    end: Option<DateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    priority: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    annotations: Vec<Annotation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    recur: Option<String>,
+    /// The recurring template a pending instance was generated from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent: Option<String>,
+}
+
+/// A local date and, unless it is midnight, its time of day.
+type DateTime = (NaiveDate, Option<NaiveTime>);
+
+/// Taskwarrior dates are UTC timestamps such as `20240105T230000Z`; marktask keeps
+/// the local date and time of day.
+mod tw_date {
+    use super::DateTime;
+    use crate::dates::utc_timestamp;
+    use chrono::{Local, NaiveDateTime, NaiveTime, TimeZone, Utc};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    const FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+    pub fn serialize<S>(date: &Option<DateTime>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match date {
+            Some((date, time)) => serializer.serialize_str(&utc_timestamp(*date, *time)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<DateTime>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let Some(value) = Option::<String>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        let utc = NaiveDateTime::parse_from_str(&value, FORMAT)
+            .map_err(|_| de::Error::custom(format!("invalid date `{}`", value)))?;
+        let local = Utc
+            .from_utc_datetime(&utc)
+            .with_timezone(&Local)
+            .naive_local();
+        let time = Some(local.time()).filter(|&time| time != NaiveTime::MIN);
+        Ok(Some((local.date(), time)))
+    }
+}
+
+/// Reads a `task export` array.
+pub fn read_tasks<R: Read>(reader: R) -> Result<Vec<ImportedTask>> {
+    let records: Vec<Record> = serde_json::from_reader(reader).map_err(Error::json_input)?;
+    let now = Local::now().naive_local();
+    let parents: HashSet<String> = records
+        .iter()
+        .filter_map(|record| record.parent.clone())
+        .collect();
+    Ok(records
+        .into_iter()
+        // Deleted tasks are gone, and recurring templates are represented by their
+        // instances when Taskwarrior has generated any
+        .filter(|record| match record.status {
+            Status::Deleted => false,
+            Status::Recurring => !record
+                .uuid
+                .as_ref()
+                .is_some_and(|uuid| parents.contains(uuid)),
+            _ => true,
+        })
+        .map(|record| {
+            let completed = record.status == Status::Completed;
+            let end = record.end.filter(|_| completed);
+            let task = Task {
+                name: record.description,
+                completed,
+                created: record.entry.map(|(date, _)| date),
+                due: record.due.map(|(date, _)| date),
+                due_time: record.due.and_then(|(_, time)| time),
+                scheduled: record.scheduled.map(|(date, _)| date),
+                scheduled_time: record.scheduled.and_then(|(_, time)| time),
+                start: record.wait.map(|(date, _)| date),
+                start_time: record.wait.and_then(|(_, time)| time),
+                done: end.map(|(date, _)| date),
+                done_time: end.and_then(|(_, time)| time),
+                overdue: !completed
+                    && record.due.is_some_and(|(due, time)| match time {
+                        Some(time) => due.and_time(time) < now,
+                        None => due < now.date(),
+                    }),
+                priority: match record.priority.as_deref() {
+                    Some("H") => Priority::High,
+                    Some("M") => Priority::Medium,
+                    Some("L") => Priority::Low,
+                    _ => Priority::None,
+                },
+                recurrence: record.recur.as_deref().and_then(recurrence_from_recur),
+                tags: record.tags,
+                ..Default::default()
+            };
+            ImportedTask {
+                uid: record.uuid,
+                task,
+                annotations: record
+                    .annotations
+                    .into_iter()
+                    .map(|annotation| annotation.description)
+                    .collect(),
+            }
+        })
+        .collect())
+}
+
+/// Writes `tasks` as a JSON array that `task import` accepts, one task per line.
+pub fn write_tasks<W: Write>(mut writer: W, tasks: &[&Task]) -> Result<()> {
+    writer.write_all(b"[")?;
+    for (index, task) in tasks.iter().enumerate() {
+        writer.write_all(if index == 0 { b"\n" } else { b",\n" })?;
+        serde_json::to_writer(&mut writer, &record(task))?;
+    }
+    writer.write_all(b"\n]\n")?;
+    Ok(())
+}
+
+/// A stable UUID for `task`, derived from its `uid` like [`crate::ics::uid`]. A `uid`
+/// that already is a UUID, as Taskwarrior gives tasks, is kept as it is.
+pub fn uuid(task: &Task) -> String {
+    let source = task.uid.clone().unwrap_or_else(|| task.stable_id());
+    if is_uuid(&source) {
+        return source.to_lowercase();
+    }
+    let high = fnv1a(source.as_bytes());
+    let low = fnv1a(format!("{}\nuuid", source).as_bytes());
+    let mut bytes = [0u8; 16];
+    bytes[..8].copy_from_slice(&high.to_be_bytes());
+    bytes[8..].copy_from_slice(&low.to_be_bytes());
+    // Version 8 (custom) and the RFC 4122 variant
+    bytes[6] = (bytes[6] & 0x0f) | 0x80;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+fn is_uuid(text: &str) -> bool {
+    text.len() == 36
+        && text.char_indices().all(|(index, c)| match index {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+/// Converts an Obsidian Tasks recurrence rule into a Taskwarrior `recur` duration.
+pub fn recur(recurrence: &str) -> Option<String> {
+    let rule = recurrence.trim().to_lowercase();
+    if rule.strip_suffix("when done").unwrap_or(&rule).trim() == "every weekday" {
+        return Some("weekdays".to_string());
+    }
+    let interval = Interval::parse(recurrence)?;
+    Some(match (interval.count, interval.unit) {
+        (1, Unit::Day) => "daily".to_string(),
+        (1, unit) => format!("{}ly", unit.name()),
+        (count, unit) => format!("{}{}s", count, unit.name()),
+    })
+}
+
+/// Converts a Taskwarrior `recur` duration back into an Obsidian Tasks recurrence rule.
+pub fn recurrence_from_recur(recur: &str) -> Option<String> {
+    let recur = recur.trim().to_lowercase();
+    let (count, unit) = match recur.as_str() {
+        "daily" | "day" => (1, Unit::Day),
+        "weekly" | "week" => (1, Unit::Week),
+        "biweekly" | "fortnight" => (2, Unit::Week),
+        "monthly" | "month" => (1, Unit::Month),
+        "quarterly" => (3, Unit::Month),
+        "yearly" | "annual" | "year" => (1, Unit::Year),
+        "weekdays" => return Some("every weekday".to_string()),
+        _ => {
+            let regex =
+                Regex::new(r"^(\d+)\s*(d|days?|w|wks?|weeks?|mo|mths?|months?|y|yrs?|years?)$")
+                    .unwrap();
+            let caps = regex.captures(&recur)?;
+            let unit = Unit::from_letter(caps[2].chars().next()?)?;
+            (caps[1].parse().ok()?, unit)
+        }
+    };
+    Some(
+        Interval {
+            count,
+            unit,
+            when_done: false,
+        }
+        .to_string(),
+    )
+}
+
+fn record(task: &Task) -> Record {
+    // Taskwarrior only lets tasks with a due date recur
+    let recur = task
+        .recurrence
+        .as_deref()
+        .and_then(recur)
+        .filter(|_| task.due.is_some());
+    let status = if task.completed {
+        Status::Completed
+    } else if recur.is_some() {
+        Status::Recurring
+    } else {
+        Status::Pending
+    };
+    Record {
+        uuid: Some(uuid(task)),
+        // Tags are exported separately
+        description: task.name_without_tags(),
+        status,
+        entry: task.created.map(|date| (date, None)),
+        due: task.due.map(|date| (date, task.due_time)),
+        scheduled: task.scheduled.map(|date| (date, task.scheduled_time)),
+        wait: task.start.map(|date| (date, task.start_time)),
+        end: task
+            .done
+            .filter(|_| task.completed)
+            .map(|date| (date, task.done_time)),
+        priority: match task.priority {
+            Priority::Highest | Priority::High => Some("H".to_string()),
+            Priority::Medium => Some("M".to_string()),
+            Priority::Low | Priority::Lowest => Some("L".to_string()),
+            Priority::None => None,
+        },
+        tags: task.tags.clone(),
+        annotations: annotations(task.notes.as_deref()),
+        recur,
+        parent: None,
+    }
+}
+
+/// Each line of a task's notes as an annotation, without its list bullet.
+fn annotations(notes: Option<&str>) -> Vec<Annotation> {
+    notes
+        .unwrap_or_default()
+        .lines()
+        .map(|line| {
+            let line = line.trim();
+            line.strip_prefix(['-', '*', '+'])
+                .filter(|rest| rest.starts_with(' '))
+                .unwrap_or(line)
+                .trim()
+        })
+        .filter(|line| !line.is_empty())
+        .map(|line| Annotation {
+            entry: None,
+            description: line.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,353 @@
+//! User-defined output templates.
+//!
+//! A template is plain text with placeholders in braces:
+//!
+//! - `{name}` inserts a task field; see [`FIELDS`] for the full list.
+//! - `{due:%a %d %b}` formats a date field with a chrono `strftime` pattern, which
+//!   may include the time of day, taken as midnight when the task gives none (dates
+//!   default to `%Y-%m-%d`, followed by `%H:%M` when they have a time).
+//! - `{?due}...{/due}` is only rendered when the field is set (a date is present,
+//!   text is non-empty, a flag is true); `{^due}...{/due}` only when it is not.
+//! - `{{` and `}}` produce literal braces.
+//!
+//! For example, `{status} {priority_emoji} {name}{?due} (due {due:%a %d %b}){/due}`.
+
+use crate::dates::format_duration;
+use crate::error::{Error, Result};
+use crate::Task;
+use chrono::format::{Fixed, Item, Numeric, StrftimeItems};
+use chrono::{NaiveDate, NaiveTime};
+
+/// Field names available in templates.
+pub const FIELDS: [&str; 33] = [
+    "name",
+    "status",
+    "completed",
+    "created",
+    "due",
+    "due_time",
+    "scheduled",
+    "scheduled_time",
+    "start",
+    "start_time",
+    "done",
+    "done_time",
+    "overdue",
+    "not_started",
+    "due_today",
+    "due_soon",
+    "priority",
+    "priority_emoji",
+    "recurrence",
+    "estimate",
+    "urgency",
+    "tags",
+    "contexts",
+    "id",
+    "depends_on",
+    "blocked",
+    "block_id",
+    "uid",
+    "links",
+    "notes",
+    "file",
+    "line",
+    "parent",
+];
+
+/// The template reproducing marktask's plain text listing.
+pub const DEFAULT_TEMPLATE: &str = "{status} - {name}";
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+const DEFAULT_DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Field {
+        name: String,
+        format: Option<String>,
+    },
+    Section {
+        name: String,
+        inverted: bool,
+        children: Vec<Node>,
+    },
+}
+
+/// The value of a task field, as seen by a template.
+enum Value {
+    Text(String),
+    Date(Option<NaiveDate>, Option<NaiveTime>),
+    Flag(bool),
+}
+
+impl Value {
+    fn is_set(&self) -> bool {
+        match self {
+            Value::Text(text) => !text.is_empty(),
+            Value::Date(date, _) => date.is_some(),
+            Value::Flag(flag) => *flag,
+        }
+    }
+}
+
+/// A section being parsed: its opening tag (`None` for the top level) and contents so far.
+struct Frame {
+    section: Option<(String, bool)>,
+    nodes: Vec<Node>,
+}
+
+/// A parsed template, ready to render tasks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        Template::parse(DEFAULT_TEMPLATE).expect("default template is valid")
+    }
+}
+
+impl Template {
+    /// Parses template text, reporting unknown fields, bad date formats and
+    /// unbalanced sections as [`Error::Template`].
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut stack = vec![Frame {
+            section: None,
+            nodes: Vec::new(),
+        }];
+        let mut text = String::new();
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '}' => return Err(template_error("unmatched `}`")),
+                '{' => {
+                    let mut tag = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => tag.push(c),
+                            None => return Err(template_error("unclosed `{`")),
+                        }
+                    }
+                    let nodes = &mut stack.last_mut().expect("stack is never empty").nodes;
+                    if !text.is_empty() {
+                        nodes.push(Node::Text(std::mem::take(&mut text)));
+                    }
+
+                    if let Some(name) = tag.strip_prefix('/') {
+                        let frame = stack.pop().expect("stack is never empty");
+                        let children = frame.nodes;
+                        match frame.section {
+                            Some((open_name, inverted)) if open_name == name => {
+                                stack.last_mut().expect("section has a parent").nodes.push(
+                                    Node::Section {
+                                        name: open_name,
+                                        inverted,
+                                        children,
+                                    },
+                                );
+                            }
+                            _ => {
+                                return Err(template_error(&format!(
+                                    "`{{/{}}}` does not close an open section",
+                                    name
+                                )))
+                            }
+                        }
+                    } else if let Some(name) = tag.strip_prefix(['?', '^']) {
+                        check_field(name)?;
+                        let inverted = tag.starts_with('^');
+                        stack.push(Frame {
+                            section: Some((name.to_string(), inverted)),
+                            nodes: Vec::new(),
+                        });
+                    } else {
+                        let (name, format) = match tag.split_once(':') {
+                            Some((name, format)) => (name, Some(format.to_string())),
+                            None => (tag.as_str(), None),
+                        };
+                        check_field(name)?;
+                        if let Some(format) = &format {
+                            check_date_format(name, format)?;
+                        }
+                        nodes.push(Node::Field {
+                            name: name.to_string(),
+                            format,
+                        });
+                    }
+                }
+                c => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            stack
+                .last_mut()
+                .expect("stack is never empty")
+                .nodes
+                .push(Node::Text(text));
+        }
+        let frame = stack.pop().expect("stack is never empty");
+        match frame.section {
+            None => Ok(Template { nodes: frame.nodes }),
+            Some((name, _)) => Err(template_error(&format!(
+                "section `{}` is never closed with `{{/{}}}`",
+                name, name
+            ))),
+        }
+    }
+
+    /// Renders `task` with this template.
+    pub fn render(&self, task: &Task) -> String {
+        let mut output = String::new();
+        render_nodes(&self.nodes, task, &mut output);
+        output
+    }
+}
+
+fn render_nodes(nodes: &[Node], task: &Task, output: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Field { name, format } => match value(task, name) {
+                Value::Text(text) => output.push_str(&text),
+                Value::Flag(flag) => output.push_str(&flag.to_string()),
+                // Formats are checked for time zone specifiers, which would panic
+                Value::Date(Some(date), time) => match (format, time) {
+                    (Some(format), time) => output.push_str(
+                        &date
+                            .and_time(time.unwrap_or(NaiveTime::MIN))
+                            .format(format)
+                            .to_string(),
+                    ),
+                    (None, Some(time)) => output.push_str(
+                        &date
+                            .and_time(time)
+                            .format(DEFAULT_DATE_TIME_FORMAT)
+                            .to_string(),
+                    ),
+                    (None, None) => output.push_str(&date.format(DEFAULT_DATE_FORMAT).to_string()),
+                },
+                Value::Date(None, _) => {}
+            },
+            Node::Section {
+                name,
+                inverted,
+                children,
+            } => {
+                if value(task, name).is_set() != *inverted {
+                    render_nodes(children, task, output);
+                }
+            }
+        }
+    }
+}
+
+fn value(task: &Task, name: &str) -> Value {
+    match name {
+        "name" => Value::Text(task.name.clone()),
+        "status" => Value::Text(if task.completed { "[x]" } else { "[ ]" }.to_string()),
+        "completed" => Value::Flag(task.completed),
+        "created" => Value::Date(task.created, None),
+        "due" => Value::Date(task.due, task.due_time),
+        "due_time" => time(task.due_time),
+        "scheduled" => Value::Date(task.scheduled, task.scheduled_time),
+        "scheduled_time" => time(task.scheduled_time),
+        "start" => Value::Date(task.start, task.start_time),
+        "start_time" => time(task.start_time),
+        "done" => Value::Date(task.done, task.done_time),
+        "done_time" => time(task.done_time),
+        "overdue" => Value::Flag(task.overdue),
+        "not_started" => Value::Flag(task.not_started),
+        "due_today" => Value::Flag(task.due_today),
+        "due_soon" => Value::Flag(task.due_soon),
+        "priority" => Value::Text(task.priority.as_str().to_string()),
+        "priority_emoji" => Value::Text(task.priority.emoji().to_string()),
+        "recurrence" => Value::Text(task.recurrence.clone().unwrap_or_default()),
+        "estimate" => Value::Text(task.estimate.map(format_duration).unwrap_or_default()),
+        "urgency" => Value::Text(format!("{:.1}", task.urgency)),
+        "tags" => Value::Text(task.tags.join(" ")),
+        "contexts" => Value::Text(task.contexts.join(" ")),
+        "id" => Value::Text(task.id.clone().unwrap_or_default()),
+        "depends_on" => Value::Text(task.depends_on.join(",")),
+        "blocked" => Value::Flag(task.blocked),
+        "block_id" => Value::Text(task.block_id.clone().unwrap_or_default()),
+        "uid" => Value::Text(task.uid.clone().unwrap_or_default()),
+        "links" => Value::Text(task.link_targets()),
+        "notes" => Value::Text(task.notes.clone().unwrap_or_default()),
+        "file" => Value::Text(task.file.clone().unwrap_or_default()),
+        "line" => Value::Text(task.line.map(|l| l.to_string()).unwrap_or_default()),
+        "parent" => Value::Text(task.parent.map(|l| l.to_string()).unwrap_or_default()),
+        _ => unreachable!("field names are checked when parsing"),
+    }
+}
+
+fn time(time: Option<NaiveTime>) -> Value {
+    Value::Text(
+        time.map(|time| time.format("%H:%M").to_string())
+            .unwrap_or_default(),
+    )
+}
+
+fn check_field(name: &str) -> Result<()> {
+    if FIELDS.contains(&name) {
+        Ok(())
+    } else {
+        Err(template_error(&format!(
+            "unknown field `{}`: expected one of {}",
+            name,
+            FIELDS.join(", ")
+        )))
+    }
+}
+
+fn check_date_format(name: &str, format: &str) -> Result<()> {
+    if !matches!(name, "created" | "due" | "scheduled" | "start" | "done") {
+        return Err(template_error(&format!(
+            "field `{}` is not a date and cannot be formatted",
+            name
+        )));
+    }
+    // Dates have no time zone, so formatting one with a zone specifier would panic
+    if !StrftimeItems::new(format).all(|item| is_local_item(&item)) {
+        return Err(template_error(&format!("invalid date format `{}`", format)));
+    }
+    Ok(())
+}
+
+fn is_local_item(item: &Item) -> bool {
+    match item {
+        Item::Literal(_) | Item::OwnedLiteral(_) | Item::Space(_) | Item::OwnedSpace(_) => true,
+        Item::Numeric(numeric, _) => !matches!(numeric, Numeric::Internal(_)),
+        Item::Fixed(fixed) => !matches!(
+            fixed,
+            Fixed::TimezoneName
+                | Fixed::TimezoneOffsetColon
+                | Fixed::TimezoneOffsetDoubleColon
+                | Fixed::TimezoneOffsetTripleColon
+                | Fixed::TimezoneOffsetColonZ
+                | Fixed::TimezoneOffset
+                | Fixed::TimezoneOffsetZ
+                | Fixed::RFC2822
+                | Fixed::RFC3339
+                | Fixed::Internal(_)
+        ),
+        Item::Error => false,
+    }
+}
+
+fn template_error(message: &str) -> Error {
+    Error::Template(message.to_string())
+}
@@ -0,0 +1,273 @@
+//! todo.txt import and export.
+//!
+//! A todo.txt line such as `(A) 2024-01-02 Call mom +Family @phone due:2024-01-05`
+//! maps onto a [`Task`]: priority letters A to E become [`Priority`] levels (F to Z
+//! count as lowest), `+project` and `@context` words become tags and contexts, and
+//! the `due:`, `t:` (threshold, i.e. start) and `scheduled:` keys set dates. Simple
+//! recurrence rules travel as `rec:`, where `rec:+1w` repeats on schedule and `rec:1w`
+//! repeats a week after completion. The completion and creation dates after `x` or the
+//! priority become the done (✅) and created (➕) dates. todo.txt dates have no time of
+//! day, so times travel in `due_time:`, `start_time:`, `scheduled_time:` and
+//! `done_time:` keys, such as `due:2024-01-05 due_time:14:00`.
+
+use crate::error::Result;
+use crate::recurrence::{split_repeater, Interval};
+use crate::{Diagnostic, ParsedInput, Priority, Task};
+use chrono::{Local, NaiveDate, NaiveTime};
+use std::io::{Read, Write};
+use std::iter::Peekable;
+
+/// Parses todo.txt text, one task per non-blank line.
+pub fn parse(input: &str) -> ParsedInput {
+    let mut tasks = Vec::new();
+    let mut diagnostics = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        if let Some(task) = parse_line(index + 1, line, &mut diagnostics) {
+            tasks.push(task);
+        }
+    }
+    ParsedInput { tasks, diagnostics }
+}
+
+/// Reads all of `reader` and parses it as todo.txt.
+pub fn read_tasks<R: Read>(mut reader: R) -> Result<ParsedInput> {
+    let mut input = String::new();
+    reader.read_to_string(&mut input)?;
+    Ok(parse(&input))
+}
+
+/// Writes `tasks` as todo.txt lines.
+pub fn write_tasks<W: Write>(mut writer: W, tasks: &[&Task]) -> Result<()> {
+    for task in tasks {
+        writeln!(writer, "{}", render_task(task))?;
+    }
+    Ok(())
+}
+
+/// Renders `task` as a todo.txt line.
+///
+/// Markdown `#tags` in the name are written as `+projects`. Completed tasks keep their
+/// priority as `pri:`, since todo.txt drops the `(A)` prefix on completion.
+pub fn render_task(task: &Task) -> String {
+    let mut words = Vec::new();
+    let letter = priority_letter(task.priority);
+    if task.completed {
+        words.push("x".to_string());
+        if let Some(done) = task.done {
+            words.push(done.format("%Y-%m-%d").to_string());
+        }
+    } else if let Some(letter) = letter {
+        words.push(format!("({})", letter));
+    }
+    // todo.txt only allows a completed task a creation date after its completion date
+    if let Some(created) = task
+        .created
+        .filter(|_| !task.completed || task.done.is_some())
+    {
+        words.push(created.format("%Y-%m-%d").to_string());
+    }
+    for word in task.name.split_whitespace() {
+        match word.strip_prefix('#') {
+            Some(tag) if task.tags.iter().any(|t| t == tag) => words.push(format!("+{}", tag)),
+            _ => words.push(word.to_string()),
+        }
+    }
+    let extra = task
+        .tags
+        .iter()
+        .map(|tag| format!("+{}", tag))
+        .chain(task.contexts.iter().map(|context| format!("@{}", context)));
+    for word in extra {
+        if !words.contains(&word) {
+            words.push(word);
+        }
+    }
+    if task.completed {
+        if let Some(letter) = letter {
+            words.push(format!("pri:{}", letter));
+        }
+    }
+    for (key, date) in [
+        ("due", task.due),
+        ("t", task.start),
+        ("scheduled", task.scheduled),
+    ] {
+        if let Some(date) = date {
+            words.push(format!("{}:{}", key, date.format("%Y-%m-%d")));
+        }
+    }
+    // todo.txt dates have no time of day, so times go in keys of their own
+    for (key, date, time) in [
+        ("due_time", task.due, task.due_time),
+        ("start_time", task.start, task.start_time),
+        ("scheduled_time", task.scheduled, task.scheduled_time),
+        ("done_time", task.done, task.done_time),
+    ] {
+        if let (Some(_), Some(time)) = (date, time) {
+            words.push(format!("{}:{}", key, time.format("%H:%M")));
+        }
+    }
+    if let Some(value) = task.recurrence.as_deref().and_then(rec) {
+        words.push(format!("rec:{}", value));
+    }
+    words.join(" ")
+}
+
+/// Converts an Obsidian Tasks recurrence rule into a todo.txt `rec:` value, when it
+/// is a plain interval such as "every 2 weeks".
+pub fn rec(recurrence: &str) -> Option<String> {
+    let interval = Interval::parse(recurrence)?;
+    let strict = if interval.when_done { "" } else { "+" };
+    Some(format!("{}{}", strict, interval.repeater()))
+}
+
+/// Converts a todo.txt `rec:` value back into an Obsidian Tasks recurrence rule.
+pub fn recurrence_from_rec(value: &str) -> Option<String> {
+    let (marks, count, unit) = split_repeater(value)?;
+    let when_done = match marks {
+        "" => true,
+        "+" => false,
+        _ => return None,
+    };
+    Some(
+        Interval {
+            count,
+            unit,
+            when_done,
+        }
+        .to_string(),
+    )
+}
+
+fn parse_line(line_number: usize, line: &str, diagnostics: &mut Vec<Diagnostic>) -> Option<Task> {
+    let mut tokens = line
+        .split_whitespace()
+        .map(|token| (column_of(line, token), token))
+        .peekable();
+    tokens.peek()?;
+
+    let mut task = Task {
+        line: Some(line_number),
+        ..Default::default()
+    };
+    if tokens.next_if(|(_, token)| *token == "x").is_some() {
+        task.completed = true;
+        // Completion date, then creation date
+        task.done = next_date(&mut tokens);
+    } else if let Some((_, token)) = tokens.next_if(|(_, token)| priority_token(token).is_some()) {
+        task.priority = priority_token(token).unwrap_or_default();
+    }
+    // A completed task only has a creation date after its completion date
+    if !task.completed || task.done.is_some() {
+        task.created = next_date(&mut tokens);
+    }
+
+    let mut name = Vec::new();
+    for (column, token) in tokens {
+        if let Some(tag) = token.strip_prefix('+').filter(|tag| !tag.is_empty()) {
+            task.tags.push(tag.to_string());
+            continue;
+        }
+        if let Some(context) = token.strip_prefix('@').filter(|c| !c.is_empty()) {
+            task.contexts.push(context.to_string());
+            continue;
+        }
+        let mut diagnose = |message: String| {
+            diagnostics.push(Diagnostic {
+                line: line_number,
+                column,
+                message,
+            })
+        };
+        match token.split_once(':') {
+            Some((key @ ("due" | "t" | "scheduled"), value)) => {
+                match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+                    Ok(date) => match key {
+                        "due" => task.due = Some(date),
+                        "t" => task.start = Some(date),
+                        _ => task.scheduled = Some(date),
+                    },
+                    Err(_) => diagnose(format!("invalid {} date `{}`", key, value)),
+                }
+            }
+            Some((key @ ("due_time" | "start_time" | "scheduled_time" | "done_time"), value)) => {
+                match NaiveTime::parse_from_str(value, "%H:%M") {
+                    Ok(time) => match key {
+                        "due_time" => task.due_time = Some(time),
+                        "start_time" => task.start_time = Some(time),
+                        "scheduled_time" => task.scheduled_time = Some(time),
+                        _ => task.done_time = Some(time),
+                    },
+                    Err(_) => diagnose(format!("invalid {} `{}`", key.replace('_', " "), value)),
+                }
+            }
+            Some(("rec", value)) => match recurrence_from_rec(value) {
+                Some(rule) => task.recurrence = Some(rule),
+                None => diagnose(format!("unsupported recurrence `{}`", value)),
+            },
+            Some(("pri", value)) if task.completed => {
+                match value.parse().ok().and_then(priority_from_letter) {
+                    Some(priority) => task.priority = priority,
+                    None => diagnose(format!("invalid priority `{}`", value)),
+                }
+            }
+            _ => name.push(token),
+        }
+    }
+
+    task.name = name.join(" ");
+    // A time only counts along with its date
+    task.due_time = task.due_time.filter(|_| task.due.is_some());
+    task.start_time = task.start_time.filter(|_| task.start.is_some());
+    task.scheduled_time = task.scheduled_time.filter(|_| task.scheduled.is_some());
+    task.done_time = task.done_time.filter(|_| task.done.is_some());
+    let now = Local::now().naive_local();
+    task.overdue = task.due.is_some_and(|due| match task.due_time {
+        Some(time) => due.and_time(time) < now,
+        None => due < now.date(),
+    });
+    Some(task)
+}
+
+/// Reads a `(A)` style priority prefix.
+fn priority_token(token: &str) -> Option<Priority> {
+    let letter = token.strip_prefix('(')?.strip_suffix(')')?;
+    priority_from_letter(letter.parse().ok()?)
+}
+
+fn priority_from_letter(letter: char) -> Option<Priority> {
+    match letter {
+        'A' => Some(Priority::Highest),
+        'B' => Some(Priority::High),
+        'C' => Some(Priority::Medium),
+        'D' => Some(Priority::Low),
+        'E'..='Z' => Some(Priority::Lowest),
+        _ => None,
+    }
+}
+
+fn priority_letter(priority: Priority) -> Option<char> {
+    match priority {
+        Priority::Highest => Some('A'),
+        Priority::High => Some('B'),
+        Priority::Medium => Some('C'),
+        Priority::Low => Some('D'),
+        Priority::Lowest => Some('E'),
+        Priority::None => None,
+    }
+}
+
+/// Takes the next token when it is a `YYYY-MM-DD` date.
+fn next_date<'a>(
+    tokens: &mut Peekable<impl Iterator<Item = (usize, &'a str)>>,
+) -> Option<NaiveDate> {
+    tokens
+        .next_if(|(_, token)| NaiveDate::parse_from_str(token, "%Y-%m-%d").is_ok())
+        .and_then(|(_, token)| NaiveDate::parse_from_str(token, "%Y-%m-%d").ok())
+}
+
+/// The 1-based character column at which `token`, a slice of `line`, starts.
+fn column_of(line: &str, token: &str) -> usize {
+    let offset = token.as_ptr() as usize - line.as_ptr() as usize;
+    line[..offset].chars().count() + 1
+}
@@ -0,0 +1,161 @@
+//! Urgency scores, after Taskwarrior's urgency model.
+//!
+//! A task's urgency is a sum of coefficients, each scaled by how far the task has the
+//! property it rewards: its priority, how close its due date is, whether it is overdue,
+//! whether its scheduled date has arrived or its start date is still to come, its tags
+//! and its age since the created date (➕). Completed tasks have no urgency.
+//!
+//! The coefficients can be changed in the `[urgency]` section of the configuration
+//! file, where `[urgency.tag]` gives extra urgency to tasks with particular tags:
+//!
+//! ```toml
+//! [urgency]
+//! due = 12.0
+//! waiting = -3.0
+//!
+//! [urgency.priority]
+//! low = 0.0
+//!
+//! [urgency.tag]
+//! next = 15.0
+//! ```
+
+use crate::{Priority, Task};
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Coefficients of the urgency model.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UrgencyModel {
+    pub priority: PriorityUrgency,
+    /// Scaled from 0.2, two weeks or more before the due date, to 1, a week after it.
+    pub due: f64,
+    pub overdue: f64,
+    /// Applies once the scheduled date (⏳) has arrived.
+    pub scheduled: f64,
+    /// Applies while the start date (🛫) is still to come.
+    pub waiting: f64,
+    /// Scaled by the number of tags: 0.8 for one, 0.9 for two and 1 for more.
+    pub tags: f64,
+    /// Extra urgency for tasks with each of these tags.
+    pub tag: HashMap<String, f64>,
+    /// Scaled by the days since the created date (➕), up to `age_max_days`.
+    pub age: f64,
+    pub age_max_days: u32,
+}
+
+impl Default for UrgencyModel {
+    fn default() -> Self {
+        UrgencyModel {
+            priority: PriorityUrgency::default(),
+            due: 12.0,
+            overdue: 2.0,
+            scheduled: 5.0,
+            waiting: -3.0,
+            tags: 1.0,
+            tag: HashMap::new(),
+            age: 2.0,
+            age_max_days: 365,
+        }
+    }
+}
+
+/// Urgency coefficients of each priority. As in Obsidian Tasks, low and lowest
+/// priorities rank below tasks without one.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PriorityUrgency {
+    pub highest: f64,
+    pub high: f64,
+    pub medium: f64,
+    pub low: f64,
+    pub lowest: f64,
+    pub none: f64,
+}
+
+impl Default for PriorityUrgency {
+    fn default() -> Self {
+        PriorityUrgency {
+            highest: 9.0,
+            high: 6.0,
+            medium: 3.9,
+            low: -1.8,
+            lowest: -3.6,
+            none: 0.0,
+        }
+    }
+}
+
+impl PriorityUrgency {
+    fn coefficient(&self, priority: Priority) -> f64 {
+        match priority {
+            Priority::Highest => self.highest,
+            Priority::High => self.high,
+            Priority::Medium => self.medium,
+            Priority::Low => self.low,
+            Priority::Lowest => self.lowest,
+            Priority::None => self.none,
+        }
+    }
+}
+
+impl UrgencyModel {
+    /// Scores `task` at `now`, rounded to two decimal places.
+    pub fn score(&self, task: &Task, now: NaiveDateTime) -> f64 {
+        if task.completed {
+            return 0.0;
+        }
+        let today = now.date();
+        let mut urgency = self.priority.coefficient(task.priority);
+
+        if let Some(due) = task.due_at() {
+            let days_overdue = (now - due).num_minutes() as f64 / (24.0 * 60.0);
+            let factor = if days_overdue >= 7.0 {
+                1.0
+            } else if days_overdue >= -14.0 {
+                (days_overdue + 14.0) * 0.8 / 21.0 + 0.2
+            } else {
+                0.2
+            };
+            urgency += self.due * factor;
+        }
+        // Tasks due on a day, rather than at a time, are overdue from the next day
+        let overdue = match (task.due, task.due_time) {
+            (Some(due), Some(time)) => due.and_time(time) < now,
+            (Some(due), None) => due < today,
+            _ => false,
+        };
+        if overdue {
+            urgency += self.overdue;
+        }
+        if task.scheduled.is_some_and(|scheduled| scheduled <= today) {
+            urgency += self.scheduled;
+        }
+        if task.start.is_some_and(|start| start > today) {
+            urgency += self.waiting;
+        }
+
+        urgency += self.tags
+            * match task.tags.len() {
+                0 => 0.0,
+                1 => 0.8,
+                2 => 0.9,
+                _ => 1.0,
+            };
+        urgency += task
+            .tags
+            .iter()
+            .filter_map(|tag| self.tag.get(tag))
+            .sum::<f64>();
+
+        if let Some(created) = task.created {
+            let days = (today - created).num_days().max(0) as f64;
+            let max_days = f64::from(self.age_max_days.max(1));
+            urgency += self.age * (days / max_days).min(1.0);
+        }
+
+        (urgency * 100.0).round() / 100.0
+    }
+}
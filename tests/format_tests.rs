@@ -0,0 +1,1014 @@
+use chrono::{Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use marktask::color::{ColorChoice, Theme};
+use marktask::config::Config;
+use marktask::csv::{self, Column, Delimiter};
+use marktask::markdown::Syntax;
+use marktask::recurrence::{split_repeater, Interval, Unit};
+use marktask::template::Template;
+use marktask::{
+    dependencies, ics, json, logseq, markdown, org, parse_input, table, taskwarrior, todotxt,
+    Priority, Task,
+};
+
+fn render_csv(input: &str, columns: &[Column], delimiter: Delimiter) -> String {
+    let tasks = parse_input(input).tasks;
+    let task_refs: Vec<&Task> = tasks.iter().collect();
+    let mut buffer = Vec::new();
+    csv::write_tasks(&mut buffer, &task_refs, columns, delimiter).expect("Failed to write");
+    String::from_utf8(buffer).expect("Output is not UTF-8")
+}
+
+#[test]
+fn test_csv_quoting() {
+    let input = "- [ ] Buy \"milk\", eggs ⏫ 📅 2024-01-02\n- [x] Plain task";
+    let columns = csv::parse_columns("name,due,priority,completed,line").expect("Valid columns");
+
+    let output = render_csv(input, &columns, Delimiter::Comma);
+
+    assert_eq!(
+        output,
+        "name,due,priority,completed,line\r\n\
+         \"Buy \"\"milk\"\", eggs\",2024-01-02,high,false,1\r\n\
+         Plain task,,none,true,2\r\n"
+    );
+}
+
+#[test]
+fn test_csv_times() {
+    let output = render_csv(
+        "- [ ] Standup ⏳ 2024-02-10 09:30 📅 2024-02-10\n- [x] Review ✅ 2024-02-12 14:00",
+        &csv::parse_columns("due,due_time,scheduled,scheduled_time,done,done_time")
+            .expect("Valid columns"),
+        Delimiter::Comma,
+    );
+
+    assert_eq!(
+        output,
+        "due,due_time,scheduled,scheduled_time,done,done_time\r\n\
+         2024-02-10,,2024-02-10 09:30,09:30,,\r\n\
+         ,,,,2024-02-12 14:00,14:00\r\n"
+    );
+}
+
+#[test]
+fn test_tsv_output() {
+    let output = render_csv(
+        "- [ ] Call\tBob 🔽",
+        &[Column::Name, Column::Priority],
+        Delimiter::Tab,
+    );
+
+    assert_eq!(output, "name\tpriority\nCall Bob\tlow\n");
+}
+
+#[test]
+fn test_unknown_column() {
+    assert!(csv::parse_columns("name,colour").is_err());
+    assert_eq!(
+        csv::parse_columns("status, file").expect("Valid columns"),
+        vec![Column::Completed, Column::File]
+    );
+    for column in Column::ALL {
+        assert_eq!(column.name().parse::<Column>().ok(), Some(column));
+    }
+}
+
+#[test]
+fn test_dependency_columns() {
+    let mut tasks = parse_input(
+        "- [ ] Ship 🆔 ship ⛔ build,docs\n\
+         - [ ] Build 🆔 build\n\
+         - [x] Docs 🆔 docs",
+    )
+    .tasks;
+    let open_ids = dependencies::open_ids(&tasks.iter().collect::<Vec<_>>());
+    for task in &mut tasks {
+        task.blocked = dependencies::is_blocked(task, &open_ids);
+    }
+    let task_refs: Vec<&Task> = tasks.iter().collect();
+
+    let mut buffer = Vec::new();
+    let columns = csv::parse_columns("id,depends_on,blocked").expect("Valid columns");
+    csv::write_tasks(&mut buffer, &task_refs, &columns, Delimiter::Comma).expect("Failed to write");
+    assert_eq!(
+        String::from_utf8(buffer).expect("Output is not UTF-8"),
+        "id,depends_on,blocked\r\n\
+         ship,\"build,docs\",true\r\n\
+         build,,false\r\n\
+         docs,,false\r\n"
+    );
+
+    let template =
+        Template::parse("{id}{?blocked} waits on {depends_on}{/blocked}").expect("Valid template");
+    let rendered: Vec<String> = tasks.iter().map(|task| template.render(task)).collect();
+    assert_eq!(rendered, vec!["ship waits on build,docs", "build", "docs"]);
+}
+
+#[test]
+fn test_links_column() {
+    let input = "- [ ] Read [[Meeting 2024-02-01|standup]] and [spec](https://example.com/spec)\n\
+                 - [ ] Nothing linked";
+
+    assert_eq!(
+        render_csv(input, &[Column::Links], Delimiter::Comma),
+        "links\r\n\"Meeting 2024-02-01, https://example.com/spec\"\r\n\r\n"
+    );
+
+    let tasks = parse_input(input).tasks;
+    let template =
+        Template::parse("{?links}{links}{/links}{^links}-{/links}").expect("Valid template");
+    assert_eq!(
+        template.render(&tasks[0]),
+        "Meeting 2024-02-01, https://example.com/spec"
+    );
+    assert_eq!(template.render(&tasks[1]), "-");
+}
+
+#[test]
+fn test_notes_column() {
+    let input = "- [ ] Plan trip\n  - Book \"hotel\"\n  Pack\tbags\n- [ ] No notes";
+
+    assert_eq!(
+        render_csv(input, &[Column::Name, Column::Notes], Delimiter::Comma),
+        "name,notes\r\nPlan trip,\"- Book \"\"hotel\"\"\nPack\tbags\"\r\nNo notes,\r\n"
+    );
+    assert_eq!(
+        render_csv(input, &[Column::Notes], Delimiter::Tab),
+        "notes\n- Book \"hotel\" Pack bags\n\n"
+    );
+
+    let tasks = parse_input(input).tasks;
+    let template = Template::parse("{name}{?notes}: {notes}{/notes}").expect("Valid template");
+    assert_eq!(
+        template.render(&tasks[0]),
+        "Plan trip: - Book \"hotel\"\nPack\tbags"
+    );
+    assert_eq!(template.render(&tasks[1]), "No notes");
+}
+
+fn render_table(input: &str, max_width: Option<usize>) -> String {
+    let tasks = parse_input(input).tasks;
+    let task_refs: Vec<&Task> = tasks.iter().collect();
+    let today = NaiveDate::from_ymd_opt(2024, 2, 10).expect("valid test date");
+    let mut buffer = Vec::new();
+    table::write_tasks(&mut buffer, &task_refs, today, max_width, None).expect("Failed to write");
+    String::from_utf8(buffer).expect("Output is not UTF-8")
+}
+
+#[test]
+fn test_table_relative_dates() {
+    let output = render_table(
+        "- [ ] Pay rent ⏫ 📅 2024-02-08\n- [x] Book flights ⏳ 2024-02-13",
+        None,
+    );
+
+    assert_eq!(
+        output,
+        "STATUS  PRIORITY  DUE             SCHEDULED  START  NAME          SOURCE\n\
+         [ ]     high      2 days overdue                    Pay rent      line 1\n\
+         [x]     none                      in 3 days         Book flights  line 2\n"
+    );
+}
+
+#[test]
+fn test_table_truncates_names() {
+    let output = render_table(
+        "- [ ] A rather long task name that will not fit 📅 2024-02-11",
+        Some(70),
+    );
+
+    for line in output.lines() {
+        assert!(line.chars().count() <= 70, "Line too wide: {}", line);
+    }
+    assert!(output.contains("A rather long t…"));
+}
+
+#[test]
+fn test_theme_styles() {
+    let today = NaiveDate::from_ymd_opt(2024, 2, 10).expect("valid test date");
+    let mut tasks = parse_input(
+        "- [ ] Late 📅 2020-01-01\n\
+         - [ ] Today ⏫ 📅 2024-02-10 23:59\n\
+         - [ ] Earlier today 📅 2024-02-10 09:00\n\
+         - [x] Done 🔺\n\
+         - [ ] Plain",
+    )
+    .tasks;
+    for task in &mut tasks {
+        task.update_date_flags(today, 3);
+    }
+    // As read at noon on the day, before the task due at 23:59 is overdue
+    tasks[1].overdue = false;
+    let theme = Theme::default();
+
+    let painted: Vec<String> = tasks
+        .iter()
+        .map(|task| theme.paint(task, &task.name))
+        .collect();
+
+    assert_eq!(
+        painted,
+        vec![
+            "\x1b[31mLate\x1b[0m",
+            "\x1b[33;1mToday\x1b[0m",
+            "\x1b[31mEarlier today\x1b[0m",
+            "\x1b[2mDone\x1b[0m",
+            "Plain",
+        ]
+    );
+}
+
+#[test]
+fn test_theme_from_config() {
+    let config = Config::from_toml("[colors]\noverdue = \"bold bright_red\"\ncompleted = \"\"")
+        .expect("Valid configuration");
+
+    assert_eq!(config.colors.overdue, "bold bright_red".parse().unwrap());
+    assert_eq!(config.colors.completed.paint("Done"), "Done");
+    assert_eq!(config.colors.due_today, Theme::default().due_today);
+
+    assert!(Config::from_toml("[colors]\noverdue = \"blinking\"").is_err());
+    assert!(Config::from_toml("[colours]").is_err());
+}
+
+#[test]
+fn test_color_choice() {
+    assert!(ColorChoice::Always.enabled(false));
+    assert!(!ColorChoice::Never.enabled(true));
+    assert!(!ColorChoice::Auto.enabled(false));
+}
+
+#[test]
+fn test_template_rendering() {
+    let tasks = parse_input("- [ ] Write report ⏫ 📅 2024-02-10\n- [x] Tidy desk").tasks;
+    let template = Template::parse(
+        "{status} {priority_emoji}{?priority_emoji} {/priority_emoji}{name}\
+         {?due} (due {due:%a %d %b}){/due}{^due} (whenever){/due} {{line {line}}}",
+    )
+    .expect("Valid template");
+
+    let rendered: Vec<String> = tasks.iter().map(|task| template.render(task)).collect();
+
+    assert_eq!(
+        rendered,
+        vec![
+            "[ ] ⏫ Write report (due Sat 10 Feb) {line 1}",
+            "[x] Tidy desk (whenever) {line 2}",
+        ]
+    );
+    assert_eq!(Template::default().render(&tasks[1]), "[x] - Tidy desk");
+
+    let timed = parse_input(
+        "- [ ] Standup ⏳ 2024-02-10 09:30 📅 2024-02-10\n- [ ] Review 📅 2024-02-12 14:00",
+    )
+    .tasks;
+    let template = Template::parse(
+        "{name} {due}{?scheduled_time} at {scheduled_time}{/scheduled_time} {due:%H:%M}",
+    )
+    .expect("Valid template");
+    assert_eq!(
+        template.render(&timed[0]),
+        "Standup 2024-02-10 at 09:30 00:00"
+    );
+    assert_eq!(template.render(&timed[1]), "Review 2024-02-12 14:00 14:00");
+
+    let done = parse_input("- [x] Filed taxes ✅ 2024-02-10").tasks;
+    let template = Template::parse("{name} on {done:%d %b}").expect("Valid template");
+    assert_eq!(template.render(&done[0]), "Filed taxes on 10 Feb");
+}
+
+#[test]
+fn test_template_errors() {
+    for source in [
+        "{unknown}",
+        "{name:%Y}",
+        "{due:%Q}",
+        "{due:%H:%M %Z}",
+        "{done:%+}",
+        "{scheduled:%d %Z}",
+        "{start:%z}",
+        "{?due}never closed",
+        "{?due}mismatched{/start}",
+        "unclosed {name",
+        "stray }",
+    ] {
+        assert!(
+            Template::parse(source).is_err(),
+            "Template should be rejected: {}",
+            source
+        );
+    }
+}
+
+#[test]
+fn test_ics_export() {
+    let mut tasks = parse_input(
+        "- [ ] Pay rent, now; please ⏫ 🔁 every month 📅 2024-02-01 🛫 2024-01-25\n\
+         - [x] Gym 🔁 every monday and friday\n\
+         - [ ] Dentist 🛫 2024-02-01 📅 2024-02-05 10:30",
+    )
+    .tasks;
+    tasks[0].file = Some("home.md".to_string());
+    let task_refs: Vec<&Task> = tasks.iter().collect();
+    let now = Utc.with_ymd_and_hms(2024, 1, 20, 9, 30, 0).unwrap();
+
+    let mut buffer = Vec::new();
+    ics::write_calendar(&mut buffer, &task_refs, now).expect("Failed to write");
+    let output = String::from_utf8(buffer).expect("Output is not UTF-8");
+
+    let expected = format!(
+        "BEGIN:VCALENDAR\r\n\
+         VERSION:2.0\r\n\
+         PRODID:-//marktask//marktask {}//EN\r\n\
+         BEGIN:VTODO\r\n\
+         UID:{}\r\n\
+         DTSTAMP:20240120T093000Z\r\n\
+         SUMMARY:Pay rent\\, now\\; please\r\n\
+         DTSTART;VALUE=DATE:20240125\r\n\
+         DUE;VALUE=DATE:20240201\r\n\
+         PRIORITY:3\r\n\
+         STATUS:NEEDS-ACTION\r\n\
+         RRULE:FREQ=MONTHLY\r\n\
+         END:VTODO\r\n\
+         BEGIN:VTODO\r\n\
+         UID:{}\r\n\
+         DTSTAMP:20240120T093000Z\r\n\
+         SUMMARY:Gym\r\n\
+         STATUS:COMPLETED\r\n\
+         RRULE:FREQ=WEEKLY;BYDAY=MO,FR\r\n\
+         END:VTODO\r\n\
+         BEGIN:VTODO\r\n\
+         UID:{}\r\n\
+         DTSTAMP:20240120T093000Z\r\n\
+         SUMMARY:Dentist\r\n\
+         DTSTART:20240201T000000\r\n\
+         DUE:20240205T103000\r\n\
+         STATUS:NEEDS-ACTION\r\n\
+         END:VTODO\r\n\
+         END:VCALENDAR\r\n",
+        env!("CARGO_PKG_VERSION"),
+        ics::uid(&tasks[0]),
+        ics::uid(&tasks[1]),
+        ics::uid(&tasks[2]),
+    );
+    assert_eq!(output, expected);
+    assert_ne!(ics::uid(&tasks[0]), ics::uid(&tasks[1]));
+}
+
+#[test]
+fn test_ics_line_folding() {
+    let name = "é".repeat(60);
+    let tasks = parse_input(&format!("- [ ] {}", name)).tasks;
+    let now = Utc.with_ymd_and_hms(2024, 1, 20, 9, 30, 0).unwrap();
+
+    let mut buffer = Vec::new();
+    ics::write_calendar(&mut buffer, &[&tasks[0]], now).expect("Failed to write");
+    let output = String::from_utf8(buffer).expect("Folding split a UTF-8 sequence");
+
+    assert!(output.split("\r\n").all(|line| line.len() <= 75));
+    assert!(output
+        .replace("\r\n ", "")
+        .contains(&format!("SUMMARY:{}\r\n", name)));
+}
+
+#[test]
+fn test_rrule_conversion() {
+    let cases = [
+        ("every day", Some("FREQ=DAILY")),
+        ("every 2 weeks", Some("FREQ=WEEKLY;INTERVAL=2")),
+        ("every year when done", Some("FREQ=YEARLY")),
+        ("every weekday", Some("FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR")),
+        ("every Tuesday, Thursday", Some("FREQ=WEEKLY;BYDAY=TU,TH")),
+        (
+            "every week on Monday, Friday",
+            Some("FREQ=WEEKLY;BYDAY=MO,FR"),
+        ),
+        ("every month on the last Friday", None),
+    ];
+    for (recurrence, expected) in cases {
+        assert_eq!(
+            ics::rrule(recurrence).as_deref(),
+            expected,
+            "Recurrence: {}",
+            recurrence
+        );
+    }
+}
+
+#[test]
+fn test_recurrence_intervals() {
+    let biweekly = Interval {
+        count: 2,
+        unit: Unit::Week,
+        when_done: false,
+    };
+    assert_eq!(Interval::parse("Every 2 weeks"), Some(biweekly));
+    assert_eq!(biweekly.to_string(), "every 2 weeks");
+    assert_eq!(biweekly.repeater(), "2w");
+    let daily = Interval::parse("every day when done").expect("Valid rule");
+    assert_eq!(
+        (daily.count, daily.unit, daily.when_done),
+        (1, Unit::Day, true)
+    );
+    assert_eq!(daily.to_string(), "every day when done");
+    assert_eq!(Interval::parse("every monday"), None);
+
+    assert_eq!(split_repeater(".+3m"), Some((".+", 3, Unit::Month)));
+    assert_eq!(split_repeater("1y"), Some(("", 1, Unit::Year)));
+    assert_eq!(split_repeater("10:00-11:30"), None);
+    assert_eq!(split_repeater("+w"), None);
+
+    // Every format reads the rule the same way
+    for (rule, rec, repeater, recur, rrule) in [
+        (
+            "every 2 weeks",
+            "+2w",
+            "+2w",
+            "2weeks",
+            "FREQ=WEEKLY;INTERVAL=2",
+        ),
+        (
+            "every month when done",
+            "1m",
+            ".+1m",
+            "monthly",
+            "FREQ=MONTHLY",
+        ),
+    ] {
+        assert_eq!(todotxt::rec(rule).as_deref(), Some(rec));
+        assert_eq!(todotxt::recurrence_from_rec(rec).as_deref(), Some(rule));
+        assert_eq!(org::repeater(rule).as_deref(), Some(repeater));
+        let stamp = format!("2024-02-10 Sat {}", repeater);
+        assert_eq!(
+            org::parse(&format!("* TODO Task\n  DEADLINE: <{}>", stamp)).tasks[0]
+                .recurrence
+                .as_deref(),
+            Some(rule)
+        );
+        assert_eq!(taskwarrior::recur(rule).as_deref(), Some(recur));
+        assert_eq!(ics::rrule(rule).as_deref(), Some(rrule));
+    }
+}
+
+#[test]
+fn test_ics_import() {
+    let calendar = "BEGIN:VCALENDAR\r\n\
+                    VERSION:2.0\r\n\
+                    BEGIN:VTODO\r\n\
+                    UID:abc-123@example.com\r\n\
+                    SUMMARY:Renew passport\\, soon\r\n\
+                    DUE;TZID=Europe/London:20240210T170000\r\n\
+                    DTSTART;VALUE=DATE:20240201\r\n\
+                    PRIORITY:2\r\n\
+                    RRULE:FREQ=WEEKLY;BYDAY=MO,FR\r\n\
+                    END:VTODO\r\n\
+                    BEGIN:VEVENT\r\n\
+                    SUMMARY:Not a task\r\n\
+                    END:VEVENT\r\n\
+                    BEGIN:VTODO\r\n\
+                    SUMMARY:A summary folded over \r\n \
+                    two lines\r\n\
+                    STATUS:COMPLETED\r\n\
+                    END:VTODO\r\n\
+                    END:VCALENDAR\r\n";
+
+    let todos = ics::read_todos(calendar.as_bytes()).expect("Valid calendar");
+
+    assert_eq!(todos.len(), 2);
+    assert_eq!(todos[0].uid.as_deref(), Some("abc-123@example.com"));
+    assert_eq!(
+        markdown::render_task(&todos[0].task, Syntax::Emoji),
+        "- [ ] Renew passport, soon ⏫ 🔁 every week on Monday, Friday 🛫 2024-02-01 📅 2024-02-10 17:00"
+    );
+    assert_eq!(todos[1].uid, None);
+    assert_eq!(
+        markdown::render_task(&todos[1].task, Syntax::Emoji),
+        "- [x] A summary folded over two lines"
+    );
+
+    let broken = "BEGIN:VTODO\r\nSUMMARY:Bad\r\nDUE:2024-02-10\r\nEND:VTODO\r\n";
+    assert!(ics::read_todos(broken.as_bytes()).is_err());
+}
+
+#[test]
+fn test_ics_round_trip() {
+    let tasks = parse_input(
+        "- [ ] Plan trip 🔽 🔁 every 3 months 🛫 2024-01-25 ⏳ 2024-01-30 09:15 📅 2024-02-01\n\
+         - [x] Call the bank 🔺 📅 2024-02-02 16:00 ✅ 2024-02-02 17:05\n\
+         - [x] Renew card ✅ 2024-02-04",
+    )
+    .tasks;
+    let task_refs: Vec<&Task> = tasks.iter().collect();
+
+    let mut buffer = Vec::new();
+    ics::write_calendar(&mut buffer, &task_refs, Utc::now()).expect("Failed to write");
+    let todos = ics::read_todos(buffer.as_slice()).expect("Valid calendar");
+
+    for (todo, task) in todos.iter().zip(&tasks) {
+        assert_eq!(todo.uid, Some(ics::uid(task)));
+        assert_eq!(todo.task.name, task.name);
+        assert_eq!(todo.task.due, task.due);
+        assert_eq!(todo.task.scheduled, task.scheduled);
+        assert_eq!(todo.task.start, task.start);
+        assert_eq!(todo.task.due_time, task.due_time);
+        assert_eq!(todo.task.scheduled_time, task.scheduled_time);
+        assert_eq!(todo.task.start_time, task.start_time);
+        assert_eq!(todo.task.priority, task.priority);
+        assert_eq!(todo.task.completed, task.completed);
+        assert_eq!(todo.task.done, task.done);
+        assert_eq!(todo.task.done_time, task.done_time);
+        assert_eq!(todo.task.recurrence, task.recurrence);
+    }
+    assert_eq!(todos.len(), 3);
+    let calendar = String::from_utf8(buffer).expect("Output is not UTF-8");
+    assert!(calendar.contains("\r\nX-MARKTASK-SCHEDULED:20240130T091500\r\n"));
+    assert!(calendar.contains("\r\nDUE;VALUE=DATE:20240201\r\n"));
+    assert!(calendar.contains("\r\nDUE:20240202T160000\r\n"));
+
+    let utc = "BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\nSUMMARY:Call\r\nDUE:20240202T160000Z\r\n\
+               END:VTODO\r\nEND:VCALENDAR\r\n";
+    let todos = ics::read_todos(utc.as_bytes()).expect("Valid calendar");
+    let due = Utc
+        .with_ymd_and_hms(2024, 2, 2, 16, 0, 0)
+        .unwrap()
+        .with_timezone(&Local);
+    assert_eq!(todos[0].task.due, Some(due.date_naive()));
+    assert_eq!(todos[0].task.due_time, Some(due.time()));
+}
+
+#[test]
+fn test_todotxt_parsing() {
+    let parsed = todotxt::parse(
+        "(A) 2024-01-02 Call mom +Family @phone due:2024-01-05\n\
+         \n\
+         x 2024-01-06 2024-01-01 Pay rent pri:B rec:+1m t:2024-01-03\n\
+         (F) Read http://example.com due:2024-02-30
+\
+         Dentist due:2024-02-04 due_time:14:00 t_time:09:00 start_time:08:00 done_time:25:00",
+    );
+
+    assert_eq!(parsed.tasks.len(), 4);
+    let call = &parsed.tasks[0];
+    assert_eq!(call.name, "Call mom");
+    assert_eq!(call.priority, Priority::Highest);
+    assert_eq!(call.tags, vec!["Family"]);
+    assert_eq!(call.contexts, vec!["phone"]);
+    assert_eq!(call.due, NaiveDate::from_ymd_opt(2024, 1, 5));
+    assert_eq!(call.created, NaiveDate::from_ymd_opt(2024, 1, 2));
+    assert_eq!(call.done, None);
+    assert_eq!(call.line, Some(1));
+
+    let rent = &parsed.tasks[1];
+    assert!(rent.completed);
+    assert_eq!(rent.name, "Pay rent");
+    assert_eq!(rent.priority, Priority::High);
+    assert_eq!(rent.recurrence.as_deref(), Some("every month"));
+    assert_eq!(rent.start, NaiveDate::from_ymd_opt(2024, 1, 3));
+    assert_eq!(rent.done, NaiveDate::from_ymd_opt(2024, 1, 6));
+    assert_eq!(rent.created, NaiveDate::from_ymd_opt(2024, 1, 1));
+
+    assert_eq!(parsed.tasks[2].name, "Read http://example.com");
+    assert_eq!(parsed.tasks[2].priority, Priority::Lowest);
+    let dentist = &parsed.tasks[3];
+    assert_eq!(dentist.name, "Dentist t_time:09:00");
+    assert_eq!(dentist.due_time, NaiveTime::from_hms_opt(14, 0, 0));
+    assert_eq!(dentist.start_time, None);
+    assert_eq!(parsed.diagnostics.len(), 2);
+    assert_eq!(parsed.diagnostics[0].line, 4);
+    assert_eq!(parsed.diagnostics[0].column, 29);
+    assert_eq!(parsed.diagnostics[1].line, 5);
+    assert_eq!(parsed.diagnostics[1].message, "invalid done time `25:00`");
+}
+
+#[test]
+fn test_todotxt_output() {
+    let tasks = parse_input(
+        "- [ ] Plan #trip with Sam ⏫ 🔁 every 2 weeks ⏳ 2024-01-30 📅 2024-02-01\n\
+         - [x] Call the bank 🔺 🔁 every month on the 1st\n\
+         - [ ] Book flights ➕ 2024-01-10\n\
+         - [x] Pack ➕ 2024-01-12 ✅ 2024-01-20\n\
+         - [x] Unpack ➕ 2024-01-22
+\
+         - [x] Dentist ⏳ 2024-02-03 08:30 📅 2024-02-04 14:00 ✅ 2024-02-04 15:10",
+    )
+    .tasks;
+    let rendered: Vec<String> = tasks.iter().map(todotxt::render_task).collect();
+
+    assert_eq!(
+        rendered,
+        vec![
+            "(B) Plan +trip with Sam due:2024-02-01 scheduled:2024-01-30 rec:+2w",
+            "x Call the bank pri:A",
+            "2024-01-10 Book flights",
+            "x 2024-01-20 2024-01-12 Pack",
+            "x Unpack",
+            "x 2024-02-04 Dentist due:2024-02-04 scheduled:2024-02-03 due_time:14:00 \
+             scheduled_time:08:30 done_time:15:10",
+        ]
+    );
+
+    let mut buffer = Vec::new();
+    let columns = csv::parse_columns("name,created,done").expect("Valid columns");
+    let task_refs: Vec<&Task> = tasks[2..4].iter().collect();
+    csv::write_tasks(&mut buffer, &task_refs, &columns, Delimiter::Tab).expect("Failed to write");
+    assert_eq!(
+        String::from_utf8(buffer).expect("Output is not UTF-8"),
+        "name\tcreated\tdone\nBook flights\t2024-01-10\t\nPack\t2024-01-12\t2024-01-20\n"
+    );
+    let template = Template::parse("{name}{?created} (added {created:%d %b}){/created}")
+        .expect("Valid template");
+    assert_eq!(template.render(&tasks[3]), "Pack (added 12 Jan)");
+    assert_eq!(template.render(&tasks[0]), "Plan #trip with Sam");
+
+    let read_back = todotxt::parse(&rendered.join("\n")).tasks;
+    assert_eq!(read_back[0].tags, tasks[0].tags);
+    assert_eq!(read_back[0].due, tasks[0].due);
+    assert_eq!(read_back[0].recurrence, tasks[0].recurrence);
+    assert_eq!(read_back[1].priority, tasks[1].priority);
+    assert_eq!(read_back[2].created, tasks[2].created);
+    assert_eq!(read_back[3].created, tasks[3].created);
+    assert_eq!(read_back[3].done, tasks[3].done);
+    assert_eq!(read_back[5].due_time, tasks[5].due_time);
+    assert_eq!(read_back[5].scheduled_time, tasks[5].scheduled_time);
+    assert_eq!(read_back[5].done_time, tasks[5].done_time);
+    assert_eq!(
+        markdown::render_task(&read_back[0], Syntax::Emoji),
+        "- [ ] Plan with Sam #trip ⏫ 🔁 every 2 weeks ⏳ 2024-01-30 📅 2024-02-01"
+    );
+}
+
+#[test]
+fn test_taskwarrior_import() {
+    let export = r#"[
+{"uuid":"6d4c3f4e-0a4b-4a8e-9d3f-1b2c3d4e5f60","description":"Fix bike","status":"pending","entry":"20240101T100000Z","due":"20240105T120000Z","wait":"20240102T120000Z","priority":"M","tags":["garage"],"annotations":[{"entry":"20240102T100000Z","description":"Needs a new chain"}],"urgency":5.2},
+{"uuid":"0b5f2c1a-3e4d-4c5b-8a69-7f8e9d0c1b2a","description":"Gone","status":"deleted"},
+{"description":"Water plants","status":"completed","due":"20240110T120000Z","recur":"2weeks"}
+]"#;
+
+    let imported = taskwarrior::read_tasks(export.as_bytes()).expect("Valid export");
+
+    assert_eq!(imported.len(), 2);
+    assert_eq!(
+        imported[0].uid.as_deref(),
+        Some("6d4c3f4e-0a4b-4a8e-9d3f-1b2c3d4e5f60")
+    );
+    assert_eq!(imported[0].annotations, vec!["Needs a new chain"]);
+    // Taskwarrior's UTC timestamps are shown in local time
+    let local = |day, hour| {
+        Utc.with_ymd_and_hms(2024, 1, day, hour, 0, 0)
+            .unwrap()
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M")
+    };
+    assert_eq!(
+        markdown::render_task(&imported[0].task, Syntax::Emoji),
+        format!(
+            "- [ ] Fix bike #garage 🔼 ➕ {} 🛫 {} 📅 {}",
+            Utc.with_ymd_and_hms(2024, 1, 1, 10, 0, 0)
+                .unwrap()
+                .with_timezone(&Local)
+                .date_naive(),
+            local(2, 12),
+            local(5, 12)
+        )
+    );
+    assert_eq!(
+        markdown::render_task(&imported[1].task, Syntax::Emoji),
+        format!("- [x] Water plants 🔁 every 2 weeks 📅 {}", local(10, 12))
+    );
+
+    assert!(
+        taskwarrior::read_tasks(r#"[{"description":"x","due":"tomorrow"}]"#.as_bytes()).is_err()
+    );
+}
+
+#[test]
+fn test_taskwarrior_round_trip() {
+    let mut tasks = parse_input(
+        "- [ ] Water plants #home 🔽 🔁 every week ➕ 2024-02-01 ⏳ 2024-02-09 📅 2024-02-10\n    \
+         - Use the rain water\n    \
+         - Not the cacti\n\
+         - [x] Call the bank 🔺 🛫 2024-01-25 08:30 ✅ 2024-01-26 17:45",
+    )
+    .tasks;
+    tasks[0].file = Some("home.md".to_string());
+    let task_refs: Vec<&Task> = tasks.iter().collect();
+
+    let mut buffer = Vec::new();
+    taskwarrior::write_tasks(&mut buffer, &task_refs).expect("Failed to write");
+    let exported: serde_json::Value = serde_json::from_slice(&buffer).expect("Invalid JSON");
+
+    assert_eq!(exported[0]["description"], "Water plants");
+    assert_eq!(exported[0]["status"], "recurring");
+    assert_eq!(exported[0]["recur"], "weekly");
+    assert_eq!(exported[0]["priority"], "L");
+    assert_eq!(exported[1]["status"], "completed");
+
+    let imported = taskwarrior::read_tasks(buffer.as_slice()).expect("Valid export");
+    assert_eq!(imported[0].uid, Some(taskwarrior::uuid(&tasks[0])));
+    assert_ne!(taskwarrior::uuid(&tasks[0]), taskwarrior::uuid(&tasks[1]));
+    assert_eq!(
+        markdown::render_task(&imported[0].task, Syntax::Emoji),
+        "- [ ] Water plants #home 🔽 🔁 every week ➕ 2024-02-01 ⏳ 2024-02-09 📅 2024-02-10"
+    );
+    assert_eq!(
+        imported[0].annotations,
+        vec!["Use the rain water", "Not the cacti"]
+    );
+    assert_eq!(exported[1]["end"].as_str().map(str::len), Some(16));
+    assert_eq!(imported[1].task.done, tasks[1].done);
+    assert_eq!(
+        imported[1].task.done_time,
+        NaiveTime::from_hms_opt(17, 45, 0)
+    );
+    assert_eq!(
+        imported[1].task.start_time,
+        NaiveTime::from_hms_opt(8, 30, 0)
+    );
+    assert_eq!(imported[0].task.due_time, None);
+    assert_eq!(imported[1].task.start, tasks[1].start);
+    assert_eq!(imported[1].task.priority, Priority::High);
+}
+
+#[test]
+fn test_exported_ids_agree() {
+    let mut tasks = parse_input("- [ ] Water plants 📅 2024-02-10 ^water\n- [ ] Tidy desk").tasks;
+    for task in &mut tasks {
+        task.file = Some("home.md".to_string());
+        task.uid = Some(task.stable_id());
+    }
+    let task_refs: Vec<&Task> = tasks.iter().collect();
+
+    let mut buffer = Vec::new();
+    json::write_tasks(&mut buffer, &task_refs).expect("Failed to write");
+    let envelope: serde_json::Value = serde_json::from_slice(&buffer).expect("Invalid JSON");
+    assert_eq!(envelope["tasks"][0]["uid"], "water");
+
+    let mut buffer = Vec::new();
+    ics::write_calendar(&mut buffer, &task_refs, Utc::now()).expect("Failed to write");
+    let calendar = String::from_utf8(buffer).expect("Output is not UTF-8");
+    assert!(calendar.contains("\r\nUID:water@marktask\r\n"));
+    assert!(calendar.contains(&format!("\r\nUID:{}@marktask\r\n", tasks[1].stable_id())));
+
+    let mut buffer = Vec::new();
+    taskwarrior::write_tasks(&mut buffer, &task_refs).expect("Failed to write");
+    let exported: serde_json::Value = serde_json::from_slice(&buffer).expect("Invalid JSON");
+    assert_eq!(exported[0]["uuid"], taskwarrior::uuid(&tasks[0]));
+
+    // The block ID keeps every identifier when the task is renamed or moved
+    let mut renamed = tasks[0].clone();
+    renamed.name = "Water the plants".to_string();
+    renamed.file = Some("garden.md".to_string());
+    renamed.uid = Some(renamed.stable_id());
+    assert_eq!(ics::uid(&renamed), ics::uid(&tasks[0]));
+    assert_eq!(taskwarrior::uuid(&renamed), taskwarrior::uuid(&tasks[0]));
+    assert_ne!(taskwarrior::uuid(&tasks[0]), taskwarrior::uuid(&tasks[1]));
+
+    renamed.uid = Some("6D4C3F4E-0A4B-4A8E-9D3F-1B2C3D4E5F60".to_string());
+    assert_eq!(
+        taskwarrior::uuid(&renamed),
+        "6d4c3f4e-0a4b-4a8e-9d3f-1b2c3d4e5f60"
+    );
+
+    let columns = csv::parse_columns("uid,block_id").expect("Valid columns");
+    let mut buffer = Vec::new();
+    csv::write_tasks(&mut buffer, &task_refs, &columns, Delimiter::Comma).expect("Failed to write");
+    assert_eq!(
+        String::from_utf8(buffer).expect("Output is not UTF-8"),
+        format!(
+            "uid,block_id\r\nwater,water\r\n{},\r\n",
+            tasks[1].stable_id()
+        )
+    );
+    let template =
+        Template::parse("{uid}{?block_id} ^{block_id}{/block_id}").expect("Valid template");
+    assert_eq!(template.render(&tasks[0]), "water ^water");
+    assert_eq!(template.render(&tasks[1]), tasks[1].stable_id());
+}
+
+#[test]
+fn test_org_parsing() {
+    let parsed = org::parse(
+        "#+TITLE: Work\n\
+         * Projects\n\
+         ** TODO [#A] Write report  DEADLINE: <2024-02-10 Sat> SCHEDULED: <2024-02-08 Thu>\n\
+         ** DONE Water plants :home:garden:\n   \
+            SCHEDULED: <2024-02-01 Thu 09:00 .+1w>\n\
+         ** TODO Sort receipts\n   \
+            DEADLINE: <2024-02-30 Fri>\n\
+         ** NOTES Not a task\n\
+         * TODOS for the week\n\
+         * DONEGAL trip\n\
+         * TODO\n\
+         * DONE :errand:",
+    );
+
+    assert_eq!(parsed.tasks.len(), 5);
+    let report = &parsed.tasks[0];
+    assert_eq!(report.name, "Write report");
+    assert_eq!(report.priority, Priority::High);
+    assert_eq!(report.due, NaiveDate::from_ymd_opt(2024, 2, 10));
+    assert_eq!(report.scheduled, NaiveDate::from_ymd_opt(2024, 2, 8));
+    assert_eq!(report.line, Some(3));
+
+    let plants = &parsed.tasks[1];
+    assert!(plants.completed);
+    assert_eq!(plants.name, "Water plants");
+    assert_eq!(plants.tags, vec!["home", "garden"]);
+    assert_eq!(plants.scheduled, NaiveDate::from_ymd_opt(2024, 2, 1));
+    assert_eq!(plants.scheduled_time, NaiveTime::from_hms_opt(9, 0, 0));
+    assert_eq!(plants.recurrence.as_deref(), Some("every week when done"));
+
+    assert_eq!(parsed.tasks[2].due, None);
+    // Keywords must be words of their own
+    assert_eq!(parsed.tasks[3].line, Some(11));
+    assert_eq!(parsed.tasks[3].name, "");
+    assert!(parsed.tasks[4].completed);
+    assert_eq!(parsed.tasks[4].name, "");
+    assert_eq!(parsed.tasks[4].tags, vec!["errand"]);
+    assert_eq!(parsed.diagnostics.len(), 1);
+    assert_eq!(parsed.diagnostics[0].line, 7);
+    assert_eq!(parsed.diagnostics[0].column, 15);
+}
+
+#[test]
+fn test_org_output() {
+    let tasks = parse_input(
+        "- [ ] Plan #trip with Sam ⏫ 🔁 every 2 weeks ⏳ 2024-01-30 14:30 📅 2024-02-01\n\
+         - [x] Call the bank",
+    )
+    .tasks;
+    let rendered: Vec<String> = tasks.iter().map(org::render_task).collect();
+
+    assert_eq!(
+        rendered,
+        vec![
+            "* TODO [#A] Plan with Sam :trip:\n  \
+             DEADLINE: <2024-02-01 Thu +2w> SCHEDULED: <2024-01-30 Tue 14:30>",
+            "* DONE Call the bank",
+        ]
+    );
+
+    let read_back = org::parse(&rendered.join("\n")).tasks;
+    assert_eq!(read_back[0].due, tasks[0].due);
+    assert_eq!(read_back[0].scheduled, tasks[0].scheduled);
+    assert_eq!(
+        read_back[0].scheduled_time,
+        NaiveTime::from_hms_opt(14, 30, 0)
+    );
+    assert_eq!(read_back[0].due_time, None);
+    assert_eq!(read_back[0].recurrence, tasks[0].recurrence);
+    assert_eq!(read_back[0].tags, tasks[0].tags);
+    assert!(read_back[1].completed);
+}
+
+#[test]
+fn test_markdown_syntax_conversion() {
+    let emoji = "# Inbox\r\n\
+                 \x20 - [ ] Write report #work ⏫ 🔁 every week 📅 2024-02-10\r\n\
+                 - [x] Send invoice ⏳ 2024-02-01 ✅ 2024-02-03\n\
+                 - [ ] Broken 📅 2024-02-30\n\
+                 Not a task 📅 2024-02-10";
+    let dataview = "# Inbox\r\n\
+                    \x20 - [ ] Write report #work [priority:: high] [repeat:: every week] [due:: 2024-02-10]\r\n\
+                    - [x] Send invoice [scheduled:: 2024-02-01] [completion:: 2024-02-03]\n\
+                    - [ ] Broken 📅 2024-02-30\n\
+                    Not a task 📅 2024-02-10";
+
+    assert_eq!(markdown::convert(emoji, Syntax::Dataview), dataview);
+    assert_eq!(markdown::convert(dataview, Syntax::Emoji), emoji);
+    assert_eq!(parse_input(emoji).tasks, parse_input(dataview).tasks);
+    assert_eq!(markdown::convert(emoji, Syntax::Emoji), emoji);
+    assert_eq!(markdown::convert(dataview, Syntax::Dataview), dataview);
+
+    // A recurrence rule ends at the tags, mentions, links and block ID after it
+    let trailing = "- [ ] Water plants 🔁 every week #home @garden [[Plants]] 📅 2024-02-10 ^water";
+    let converted = markdown::convert(trailing, Syntax::Dataview);
+    assert_eq!(
+        converted,
+        "- [ ] Water plants [repeat:: every week] #home @garden [[Plants]] [due:: 2024-02-10] ^water"
+    );
+    assert_eq!(markdown::convert(&converted, Syntax::Emoji), trailing);
+    let tasks = parse_input(trailing).tasks;
+    assert_eq!(tasks[0].recurrence.as_deref(), Some("every week"));
+    assert_eq!(tasks[0].tags, vec!["home"]);
+    assert_eq!(tasks[0].block_id.as_deref(), Some("water"));
+    assert_eq!(tasks, parse_input(&converted).tasks);
+
+    assert_eq!(
+        markdown::convert(
+            "- [ ] Mixed ⏫ [due:: 2024-02-10] 🔁 every day #habit",
+            Syntax::Emoji
+        ),
+        "- [ ] Mixed ⏫ 📅 2024-02-10 🔁 every day #habit"
+    );
+}
+
+#[test]
+fn test_logseq_parsing() {
+    let parsed = logseq::parse(
+        "title:: Work\n\
+         - TODO [#A] Write spec #work SCHEDULED: <2024-02-10 Sat>\n  \
+           deadline:: [[Feb 12th, 2024]]\n  \
+           tags:: writing, [[q1 plans]]\n\
+         \t- Notes\n\
+         \t\t- DONE Collect feedback\n\
+         \t\t  DEADLINE: <2024-02-09 Fri .+1w>\n\
+         \t- LATER Book room\n  \
+           scheduled:: 2024-02-30\n\
+         - NOWHERE is not a marker\n\
+         - CANCELED Old idea",
+    );
+
+    assert_eq!(parsed.tasks.len(), 4);
+    let spec = &parsed.tasks[0];
+    assert_eq!(spec.name, "Write spec #work");
+    assert_eq!(spec.priority, Priority::High);
+    assert_eq!(spec.tags, vec!["work", "writing", "q1 plans"]);
+    assert_eq!(spec.scheduled, NaiveDate::from_ymd_opt(2024, 2, 10));
+    assert_eq!(spec.due, NaiveDate::from_ymd_opt(2024, 2, 12));
+    assert_eq!(spec.parent, None);
+
+    let feedback = &parsed.tasks[1];
+    assert!(feedback.completed);
+    assert_eq!(feedback.due, NaiveDate::from_ymd_opt(2024, 2, 9));
+    assert_eq!(feedback.recurrence.as_deref(), Some("every week when done"));
+    assert_eq!(feedback.parent, Some(2));
+
+    let room = &parsed.tasks[2];
+    assert!(!room.completed);
+    assert_eq!(room.parent, Some(2));
+    assert_eq!(room.scheduled, None);
+    assert_eq!(parsed.diagnostics.len(), 1);
+    assert_eq!(parsed.diagnostics[0].line, 9);
+    assert_eq!(parsed.diagnostics[0].column, 15);
+
+    assert!(parsed.tasks[3].completed);
+    assert_eq!(parsed.tasks[3].parent, None);
+}
+
+#[test]
+fn test_dependency_dot() {
+    let tasks = parse_input(
+        "- [x] Draft \"outline\" 🆔 outline\n\
+         - [ ] Write chapter 🆔 chapter ⛔ outline,review\n\
+         - [ ] Unrelated",
+    )
+    .tasks;
+    let mut buffer = Vec::new();
+    dependencies::write_dot(&mut buffer, &tasks.iter().collect::<Vec<_>>())
+        .expect("Failed to write");
+
+    assert_eq!(
+        String::from_utf8(buffer).unwrap(),
+        "digraph tasks {\n  \
+           node [shape=box];\n  \
+           t0 [label=\"Draft \\\"outline\\\"\", style=dashed];\n  \
+           t1 [label=\"Write chapter\"];\n  \
+           \"review\" [style=dotted];\n  \
+           t0 -> t1;\n  \
+           \"review\" -> t1;\n\
+         }\n"
+    );
+}
+
+#[test]
+fn test_stable_ids() {
+    let with_file = |input: &str| -> Vec<Task> {
+        parse_input(input)
+            .tasks
+            .into_iter()
+            .map(|mut task| {
+                task.file = Some("notes/plan.md".to_string());
+                task
+            })
+            .collect()
+    };
+    let before =
+        with_file("- [ ] Write  report ⏫ 📅 2024-02-10\n- [ ] Ship 🆔 ship\n- [ ] Tidy ^tidy-1");
+    let after = with_file("# Moved\n\n- [x] write report 📅 2024-03-01 ✅ 2024-02-20");
+
+    assert_eq!(before[0].stable_id(), after[0].stable_id());
+    assert_eq!(before[0].stable_id().len(), 12);
+    assert_eq!(before[1].stable_id(), "ship");
+    assert_eq!(before[2].block_id.as_deref(), Some("tidy-1"));
+    assert_eq!(before[2].name, "Tidy");
+    assert_eq!(before[2].stable_id(), "tidy-1");
+
+    let input = "- [ ] Water plants 🔁 every week\r\n- [ ] Water plants\n- [ ] Tidy ^tidy-1\n";
+    let assigned = markdown::assign_block_ids(input, Some("notes/plan.md"));
+    let tasks = with_file(&assigned);
+    assert_eq!(tasks[0].recurrence.as_deref(), Some("every week"));
+    assert_eq!(tasks[0].stable_id(), with_file(input)[0].stable_id());
+    assert_ne!(tasks[0].block_id, tasks[1].block_id);
+    assert!(assigned.starts_with(&format!(
+        "- [ ] Water plants 🔁 every week ^{}\r\n",
+        tasks[0].stable_id()
+    )));
+    assert!(assigned.ends_with("- [ ] Tidy ^tidy-1\n"));
+    assert_eq!(
+        markdown::assign_block_ids(&assigned, Some("notes/plan.md")),
+        assigned
+    );
+    assert_eq!(markdown::convert(&assigned, Syntax::Emoji), assigned);
+}
@@ -1,11 +1,27 @@
-use chrono::{Duration, Local, NaiveDate};
+use chrono::{Duration, Local, NaiveDate, NaiveTime};
+use marktask::config::Config;
+use marktask::csv::{self, Delimiter};
 use marktask::dates::parse_date_arg;
+use marktask::json;
+use marktask::markdown::{self, Syntax};
+use marktask::template::Template;
+use marktask::todotxt;
+use marktask::urgency::UrgencyModel;
 use marktask::Task;
 use marktask::{
-    parse_input, parse_priority, DateRangeFilter, FilterPipeline, OverdueFilter, Priority,
+    dependencies, links, mentions, parse_input, parse_priority, parse_reader, ActiveFilter,
+    BlockedFilter, ContextFilter, DateRangeFilter, Diagnostic, Error, Filter, FilterPipeline, Link,
+    LinkFilter, LinkKind, OverdueFilter, Priority, ScheduledFilter, TaskReader,
 };
+use std::collections::HashSet;
 use std::fs;
+use std::io::Write;
 use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+fn ymd(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).expect("valid test date")
+}
 
 #[test]
 fn test_task_completion() {
@@ -14,7 +30,7 @@ fn test_task_completion() {
 
     let input = fs::read_to_string(path).expect("Failed to read tasks.txt");
 
-    let tasks = parse_input(&input);
+    let tasks = parse_input(&input).tasks;
 
     let completed_tasks: Vec<&Task> = tasks.iter().filter(|t| t.completed).collect();
     let incomplete_tasks: Vec<&Task> = tasks.iter().filter(|t| !t.completed).collect();
@@ -38,9 +54,9 @@ fn test_task_names() {
 
     let input = fs::read_to_string(path).expect("Failed to read tasks.txt");
 
-    let tasks = parse_input(&input);
+    let tasks = parse_input(&input).tasks;
 
-    let expected_names = vec![
+    let expected_names = [
         "This is a test",
         "This is finished",
         "This is not",
@@ -69,32 +85,27 @@ fn test_tasks_details() {
 
     let input = fs::read_to_string(path).expect("Failed to read task_dates.txt");
 
-    let tasks = parse_input(&input);
+    let tasks = parse_input(&input).tasks;
 
     let expected_details = vec![
         ("This a task with no due data", None, None, None),
         (
             "This is another one, but with a due date",
-            Some(NaiveDate::from_ymd(2025, 7, 14)),
-            None,
-            None,
-        ),
-        (
-            "This one is overdue",
-            Some(NaiveDate::from_ymd(2021, 7, 14)),
+            Some(ymd(2025, 7, 14)),
             None,
             None,
         ),
+        ("This one is overdue", Some(ymd(2021, 7, 14)), None, None),
         ("This one has an invalid due date", None, None, None),
         (
             "This has both a due and scheduled date",
-            Some(NaiveDate::from_ymd(2025, 7, 14)),
-            Some(NaiveDate::from_ymd(2025, 7, 14)),
+            Some(ymd(2025, 7, 14)),
+            Some(ymd(2025, 7, 14)),
             None,
         ),
         (
             "This has a wrong scheduled date",
-            Some(NaiveDate::from_ymd(2025, 7, 14)),
+            Some(ymd(2025, 7, 14)),
             None,
             None,
         ),
@@ -102,7 +113,7 @@ fn test_tasks_details() {
         (
             "This has just the due date wrong",
             None,
-            Some(NaiveDate::from_ymd(2025, 7, 14)),
+            Some(ymd(2025, 7, 14)),
             None,
         ),
         (
@@ -114,32 +125,27 @@ fn test_tasks_details() {
         (
             "This one has just a scheduled date",
             None,
-            Some(NaiveDate::from_ymd(2025, 7, 14)),
+            Some(ymd(2025, 7, 14)),
             None,
         ),
-        (
-            "To start",
-            None,
-            None,
-            Some(NaiveDate::from_ymd(2024, 2, 7)),
-        ),
+        ("To start", None, None, Some(ymd(2024, 2, 7))),
         (
             "Start and due",
-            Some(NaiveDate::from_ymd(2025, 7, 14)),
+            Some(ymd(2025, 7, 14)),
             None,
-            Some(NaiveDate::from_ymd(2024, 2, 7)),
+            Some(ymd(2024, 2, 7)),
         ),
         (
             "Start and scheduled",
             None,
-            Some(NaiveDate::from_ymd(2025, 7, 15)),
-            Some(NaiveDate::from_ymd(2024, 2, 7)),
+            Some(ymd(2025, 7, 15)),
+            Some(ymd(2024, 2, 7)),
         ),
         (
             "All dates present",
-            Some(NaiveDate::from_ymd(2025, 7, 14)),
-            Some(NaiveDate::from_ymd(2025, 7, 15)),
-            Some(NaiveDate::from_ymd(2024, 2, 7)),
+            Some(ymd(2025, 7, 14)),
+            Some(ymd(2025, 7, 15)),
+            Some(ymd(2024, 2, 7)),
         ),
         ("Start with invalid date", None, None, None),
     ];
@@ -172,10 +178,223 @@ fn test_tasks_details() {
     }
 }
 
+#[test]
+fn test_date_diagnostics() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("tests/data/task_dates.txt");
+
+    let input = fs::read_to_string(path).expect("Failed to read task_dates.txt");
+
+    let diagnostics = parse_input(&input).diagnostics;
+
+    let expected = vec![
+        (4, 40, "invalid due date `9999-12-99`"),
+        (6, 52, "invalid scheduled date `9999-99-99`"),
+        (7, 33, "invalid due date `9999-99-99`"),
+        (7, 46, "invalid scheduled date `9999-99-99`"),
+        (8, 40, "invalid due date `9999-99-99`"),
+        (9, 54, "invalid scheduled date `9999-99-99`"),
+        (15, 31, "invalid start date `9999-99-99`"),
+    ];
+    let expected: Vec<Diagnostic> = expected
+        .into_iter()
+        .map(|(line, column, message)| Diagnostic {
+            line,
+            column,
+            message: message.to_string(),
+        })
+        .collect();
+
+    assert_eq!(diagnostics, expected);
+}
+
+#[test]
+fn test_duplicate_markers_and_unknown_status() {
+    let input = "- [ ] Twice due 📅 2024-01-01 📅 2024-01-02\n\
+                 - [?] Unknown status\n\
+                 - [ ] Missing date 📅\n\
+                 - [/] In progress\n\
+                 - [-] Cancelled";
+
+    let parsed = parse_input(input);
+
+    assert_eq!(
+        parsed.tasks.len(),
+        5,
+        "Unknown status lines are still listed"
+    );
+    let completed: Vec<bool> = parsed.tasks.iter().map(|task| task.completed).collect();
+    assert_eq!(completed, [false, false, false, false, true]);
+    assert_eq!(parsed.tasks[3].name, "In progress");
+    let messages: Vec<(usize, &str)> = parsed
+        .diagnostics
+        .iter()
+        .map(|d| (d.line, d.message.as_str()))
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            (1, "duplicate due date marker 📅"),
+            (2, "unknown status symbol `?`"),
+            (3, "missing due date after 📅"),
+        ]
+    );
+}
+
+#[test]
+fn test_strict_parsing() {
+    let clean = parse_reader("- [ ] Fine 📅 2024-01-01\n".as_bytes()).expect("readable input");
+    assert_eq!(clean.strict().expect("no problems").len(), 1);
+
+    let broken = parse_reader("- [ ] Broken 📅 2024-13-01\n".as_bytes()).expect("readable input");
+    match broken.strict() {
+        Err(Error::Parse(diagnostics)) => assert_eq!(diagnostics.len(), 1),
+        _ => panic!("Expected a parse error"),
+    }
+}
+
+#[test]
+fn test_json_round_trip() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("tests/data/task_dates.txt");
+
+    let input = fs::read_to_string(path).expect("Failed to read task_dates.txt");
+    let tasks = parse_input(&input).tasks;
+
+    let mut buffer = Vec::new();
+    json::write_tasks(&mut buffer, &tasks.iter().collect::<Vec<_>>()).expect("Failed to write");
+    let read_back = json::read_tasks(buffer.as_slice()).expect("Failed to read");
+
+    assert_eq!(read_back, tasks);
+}
+
+#[test]
+fn test_json_envelope() {
+    let tasks = parse_input("- [ ] Ship it ⏫ 📅 2024-02-10").tasks;
+
+    let mut buffer = Vec::new();
+    json::write_tasks(&mut buffer, &tasks.iter().collect::<Vec<_>>()).expect("Failed to write");
+    let value: serde_json::Value = serde_json::from_slice(&buffer).expect("Invalid JSON");
+
+    assert_eq!(value["version"], 1);
+    assert!(value["generated_at"].is_string());
+    assert_eq!(
+        value["tasks"][0],
+        serde_json::json!({
+            "name": "Ship it",
+            "completed": false,
+            "created": null,
+            "due": "2024-02-10",
+            "due_time": null,
+            "scheduled": null,
+            "scheduled_time": null,
+            "start": null,
+            "start_time": null,
+            "done": null,
+            "done_time": null,
+            "overdue": true,
+            "not_started": false,
+            "due_today": false,
+            "due_soon": false,
+            "priority": "high",
+            "recurrence": null,
+            "estimate": null,
+            "urgency": 0.0,
+            "id": null,
+            "depends_on": [],
+            "block_id": null,
+            "uid": null,
+            "tags": [],
+            "contexts": [],
+            "links": [],
+            "notes": null,
+            "file": null,
+            "line": 1,
+            "parent": null,
+        })
+    );
+    assert!(json::schema(json::SCHEMA_VERSION).is_some());
+
+    let future = r#"{"version": 2, "generated_at": "2030-01-01T00:00:00Z", "tasks": []}"#;
+    assert!(matches!(
+        json::read_tasks(future.as_bytes()),
+        Err(Error::UnsupportedVersion(2))
+    ));
+
+    let malformed = r#"{"version": 1, "tasks": [{"name": "Truncated"#;
+    assert!(matches!(
+        json::read_tasks(malformed.as_bytes()),
+        Err(Error::InvalidJson(_))
+    ));
+    let not_tasks = r#"{"version": 1, "generated_at": "2030-01-01T00:00:00Z", "tasks": [1]}"#;
+    assert!(matches!(
+        json::read_tasks(not_tasks.as_bytes()),
+        Err(Error::InvalidJson(_))
+    ));
+}
+
+#[test]
+fn test_json_overdue_is_recomputed() {
+    let mut tasks = parse_input(
+        "- [ ] Past 📅 2024-02-10
+- [ ] Future 📅 2999-01-01",
+    )
+    .tasks;
+    tasks[0].overdue = false;
+    tasks[1].overdue = true;
+
+    let mut buffer = Vec::new();
+    json::write_tasks(&mut buffer, &tasks.iter().collect::<Vec<_>>()).expect("Failed to write");
+    let read_back = json::read_tasks(buffer.as_slice()).expect("Failed to read");
+
+    assert!(read_back[0].overdue);
+    assert!(!read_back[1].overdue);
+}
+
+#[test]
+fn test_streaming_reader() {
+    let input =
+        "# Notes\r\n- [ ] First 📅 2024-01-01\r\n\r\nplain text\n- [x] Second 📅 2024-13-01\n";
+
+    let mut reader = TaskReader::new(input.as_bytes());
+    let first = reader.next().expect("a task").expect("readable input");
+    assert_eq!(first.name, "First");
+    assert_eq!(first.due, Some(ymd(2024, 1, 1)));
+    assert_eq!(first.notes, None);
+    assert!(reader.diagnostics().is_empty());
+
+    let second = reader.next().expect("a task").expect("readable input");
+    assert!(second.completed);
+    assert!(reader.next().is_none());
+    assert_eq!(reader.diagnostics().len(), 1);
+    assert_eq!(reader.diagnostics()[0].line, 5);
+}
+
+#[test]
+fn test_recurrence() {
+    let tasks = parse_input(
+        "- [ ] Water plants 🔁 every week 📅 2024-02-10\n\
+         - [ ] Pay rent ⏫ 🔁 every month on the 1st\n\
+         - [ ] One-off",
+    )
+    .tasks;
+
+    assert_eq!(tasks[0].name, "Water plants");
+    assert_eq!(tasks[0].recurrence.as_deref(), Some("every week"));
+    assert_eq!(tasks[0].due, Some(ymd(2024, 2, 10)));
+    assert_eq!(tasks[1].name, "Pay rent");
+    assert_eq!(
+        tasks[1].recurrence.as_deref(),
+        Some("every month on the 1st")
+    );
+    assert_eq!(tasks[1].priority, Priority::High);
+    assert_eq!(tasks[2].recurrence, None);
+}
+
 #[test]
 fn test_overdue_tasks() {
     // Generate dynamic dates for the tasks
-    let today = Local::today().naive_local();
+    let today = Local::now().date_naive();
     let yesterday = today - Duration::days(1);
     let tomorrow = today + Duration::days(1);
 
@@ -188,10 +407,10 @@ fn test_overdue_tasks() {
     );
 
     // Parse the input string to tasks
-    let tasks = parse_input(&input);
+    let tasks = parse_input(&input).tasks;
 
     // Expected overdue statuses
-    let expected_overdue = vec![
+    let expected_overdue = [
         false, // Task due today is not considered overdue
         true,  // Task due yesterday is overdue
         false, // Task due tomorrow is not overdue
@@ -214,11 +433,11 @@ fn test_overdue_tasks() {
 #[test]
 fn test_overdue_filter_pipeline() {
     // Create a date for today and a date in the past
-    let today = Local::today().naive_local();
-    let past_date = Local::today().naive_local() - chrono::Duration::days(1);
+    let today = Local::now().date_naive();
+    let past_date = Local::now().date_naive() - chrono::Duration::days(1);
 
     // Create sample tasks
-    let tasks = vec![
+    let tasks = [
         Task {
             name: "Task due today".to_string(),
             completed: false,
@@ -227,6 +446,7 @@ fn test_overdue_filter_pipeline() {
             start: None,
             scheduled: None,
             priority: Priority::None,
+            ..Default::default()
         },
         Task {
             name: "Overdue task".to_string(),
@@ -236,6 +456,7 @@ fn test_overdue_filter_pipeline() {
             start: None,
             scheduled: None,
             priority: Priority::None,
+            ..Default::default()
         },
         Task {
             name: "No due date task".to_string(),
@@ -245,6 +466,7 @@ fn test_overdue_filter_pipeline() {
             start: None,
             scheduled: None,
             priority: Priority::None,
+            ..Default::default()
         },
     ];
 
@@ -288,11 +510,12 @@ fn test_task_priorities() {
         let task = Task {
             name: d,
             completed: false,
-            due: Some(NaiveDate::from_ymd(2022, 1, 1)), // Dummy date
+            due: Some(ymd(2022, 1, 1)), // Dummy date
             overdue: false,
             scheduled: None,
             start: None,
             priority: parse_priority(description).1,
+            ..Default::default()
         };
 
         // Assert that the parsed priority matches the expected priority
@@ -307,8 +530,8 @@ fn test_task_priorities() {
 #[test]
 fn test_date_range_filtering() {
     // Define a set of tasks with various due dates
-    let base_date = NaiveDate::from_ymd(2024, 1, 1);
-    let tasks = vec![
+    let base_date = ymd(2024, 1, 1);
+    let tasks = [
         Task {
             name: "Task 1".to_string(),
             completed: false,
@@ -317,6 +540,7 @@ fn test_date_range_filtering() {
             scheduled: None,
             start: None,
             priority: Priority::None,
+            ..Default::default()
         },
         Task {
             name: "Task 2".to_string(),
@@ -326,6 +550,7 @@ fn test_date_range_filtering() {
             scheduled: None,
             start: None,
             priority: Priority::None,
+            ..Default::default()
         },
         Task {
             name: "Task 3".to_string(),
@@ -335,6 +560,7 @@ fn test_date_range_filtering() {
             scheduled: None,
             start: None,
             priority: Priority::None,
+            ..Default::default()
         },
         Task {
             name: "Task without date".to_string(),
@@ -344,6 +570,7 @@ fn test_date_range_filtering() {
             scheduled: None,
             start: None,
             priority: Priority::None,
+            ..Default::default()
         },
     ];
 
@@ -389,8 +616,8 @@ fn test_date_range_filtering() {
 
 #[test]
 fn test_relative_date_range_filtering() {
-    let today = Local::today().naive_local();
-    let tasks = vec![
+    let today = Local::now().date_naive();
+    let tasks = [
         Task {
             name: "Task due today".to_string(),
             completed: false,
@@ -399,6 +626,7 @@ fn test_relative_date_range_filtering() {
             scheduled: None,
             start: None,
             priority: Priority::None,
+            ..Default::default()
         },
         Task {
             name: "Task due in 5 days".to_string(),
@@ -408,6 +636,7 @@ fn test_relative_date_range_filtering() {
             scheduled: None,
             start: None,
             priority: Priority::None,
+            ..Default::default()
         },
         Task {
             name: "Task due in 10 days".to_string(),
@@ -417,6 +646,7 @@ fn test_relative_date_range_filtering() {
             scheduled: None,
             start: None,
             priority: Priority::None,
+            ..Default::default()
         },
         Task {
             name: "Task without date".to_string(),
@@ -426,25 +656,26 @@ fn test_relative_date_range_filtering() {
             scheduled: None,
             start: None,
             priority: Priority::None,
+            ..Default::default()
         },
     ];
 
     // Define test cases with relative dates
     let test_cases = vec![
         (
-            parse_date_arg(Some("-1d")),
-            parse_date_arg(Some("+2w")),
+            parse_date_arg(Some("-1d")).unwrap(),
+            parse_date_arg(Some("+2w")).unwrap(),
             3,
             "Tasks within last day to next week",
         ),
         (
             None,
-            parse_date_arg(Some("+1w")),
+            parse_date_arg(Some("+1w")).unwrap(),
             2,
             "Tasks up to one week from today",
         ),
         (
-            parse_date_arg(Some("-1d")),
+            parse_date_arg(Some("-1d")).unwrap(),
             None,
             3,
             "Tasks from yesterday onwards",
@@ -469,3 +700,597 @@ fn test_relative_date_range_filtering() {
         );
     }
 }
+
+#[test]
+fn test_tags() {
+    let tasks = parse_input("- [ ] Review #work/urgent PR #42 for #Sam 📅 2024-02-10").tasks;
+
+    assert_eq!(tasks[0].name, "Review #work/urgent PR #42 for #Sam");
+    assert_eq!(tasks[0].tags, vec!["work/urgent", "Sam"]);
+}
+
+#[test]
+fn test_dataview_fields() {
+    let parsed = parse_input(
+        "- [ ] Write report [priority:: high] [repeat:: every week] [due:: 2024-02-10] #work\n\
+         - [x] Send invoice (scheduled:: 2024-02-01) [completion:: 2024-02-03] [start::2024-01-30]\n\
+         - [x] Pay rent ✅ 2024-02-01\n\
+         - [ ] Broken [due:: 2024-02-30] [priority:: urgent]",
+    );
+
+    let report = &parsed.tasks[0];
+    assert_eq!(report.name, "Write report #work");
+    assert_eq!(report.priority, Priority::High);
+    assert_eq!(report.recurrence.as_deref(), Some("every week"));
+    assert_eq!(report.due, Some(ymd(2024, 2, 10)));
+
+    let invoice = &parsed.tasks[1];
+    assert_eq!(invoice.name, "Send invoice");
+    assert_eq!(invoice.scheduled, Some(ymd(2024, 2, 1)));
+    assert_eq!(invoice.start, Some(ymd(2024, 1, 30)));
+    assert_eq!(invoice.done, Some(ymd(2024, 2, 3)));
+
+    assert_eq!(parsed.tasks[2].name, "Pay rent");
+    assert_eq!(parsed.tasks[2].done, Some(ymd(2024, 2, 1)));
+
+    let messages: Vec<String> = parsed.diagnostics.iter().map(|d| d.to_string()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "4:14: invalid due date `2024-02-30`",
+            "4:33: unknown priority `urgent`"
+        ]
+    );
+}
+
+#[test]
+fn test_markdown_structure() {
+    let input = "---\n\
+                 todo: - [ ] In front matter\n\
+                 ---\n\
+                 * [ ] Star marker\n\
+                 + [X] Plus marker\n\
+                 1. [ ] Ordered 📅 2024-02-10\n\
+                 2) [ ] Parenthesised\n\
+                 > - [ ] Quoted\n\
+                 > > 3. [x] Nested quote\n\
+                 ```markdown\n\
+                 - [ ] In a code fence\n\
+                 ```\n\
+                 <!-- - [ ] Commented out\n\
+                 - [ ] Still commented -->\n\
+                 \n    \
+                 - [ ] Indented code\n\
+                 - [ ] Parent\n    \
+                     - [ ] Child\n\
+                 -[ ] Missing space\n\
+                 - [ ]Glued\n\
+                 - [-] Cancelled";
+
+    let parsed = parse_input(input);
+    let names: Vec<&str> = parsed.tasks.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "Star marker",
+            "Plus marker",
+            "Ordered",
+            "Parenthesised",
+            "Quoted",
+            "Nested quote",
+            "Parent",
+            "Child",
+            "Cancelled",
+        ]
+    );
+    assert!(parsed.tasks[1].completed);
+    assert_eq!(parsed.tasks[2].due, Some(ymd(2024, 2, 10)));
+    assert_eq!(parsed.tasks[5].line, Some(9));
+    assert!(parsed.tasks[5].completed);
+    assert!(parsed.diagnostics.is_empty());
+}
+
+#[test]
+fn test_task_notes() {
+    let input = "- [ ] Plan trip 📅 2024-03-01\n  \
+                   Book before prices go up\n  \
+                   - [Flights](https://example.com)\n    \
+                     - compare airlines\n\n  \
+                   - [ ] Renew passport\n    \
+                     Takes six weeks\n  \
+                   - hotel near the station\n\
+                 - [ ] Water plants\n\
+                 \n\
+                 Unrelated paragraph";
+
+    let tasks = parse_input(input).tasks;
+    let notes: Vec<(&str, Option<&str>)> = tasks
+        .iter()
+        .map(|t| (t.name.as_str(), t.notes.as_deref()))
+        .collect();
+    assert_eq!(
+        notes,
+        vec![
+            (
+                "Plan trip",
+                Some(
+                    "Book before prices go up\n\
+                     - [Flights](https://example.com)\n  \
+                       - compare airlines\n\
+                     \n\
+                     - hotel near the station"
+                )
+            ),
+            ("Renew passport", Some("Takes six weeks")),
+            ("Water plants", None),
+        ]
+    );
+
+    let rendered = markdown::render_task(&tasks[1], Syntax::Emoji);
+    assert_eq!(rendered, "- [ ] Renew passport\n  Takes six weeks");
+    assert_eq!(parse_input(&rendered).tasks[0].notes, tasks[1].notes);
+}
+
+#[test]
+fn test_links() {
+    let tasks = parse_input(
+        "- [ ] Follow up on [[Meeting 2024-02-01|standup]] about [spec](https://example.com/spec) 📅 2024-02-10\n\
+         - [ ] Redraw ![[diagram.png]] for [[Projects/Spec.md#Scope]]\n\
+         - [ ] Call [the bank](<Contacts/Bank.md>)",
+    )
+    .tasks;
+
+    assert_eq!(
+        tasks[0].links,
+        vec![
+            Link {
+                target: "Meeting 2024-02-01".to_string(),
+                alias: Some("standup".to_string()),
+                kind: LinkKind::Wikilink,
+            },
+            Link {
+                target: "https://example.com/spec".to_string(),
+                alias: Some("spec".to_string()),
+                kind: LinkKind::Markdown,
+            },
+        ]
+    );
+    assert_eq!(tasks[0].due, Some(ymd(2024, 2, 10)));
+    assert_eq!(
+        links::strip(&tasks[0].name),
+        "Follow up on standup about spec"
+    );
+    assert_eq!(tasks[1].links[0].kind, LinkKind::Embed);
+    assert_eq!(tasks[1].links[1].note(), Some("Spec"));
+    assert_eq!(tasks[0].links[1].note(), None);
+
+    let refs: Vec<&Task> = tasks.iter().collect();
+    let linking = |note: &str| -> Vec<usize> {
+        LinkFilter {
+            note: note.to_string(),
+        }
+        .apply(refs.clone())
+        .iter()
+        .filter_map(|task| task.line)
+        .collect()
+    };
+    assert_eq!(linking("spec"), vec![2]);
+    assert_eq!(linking("People/Bank.md"), vec![3]);
+    assert_eq!(linking("meeting 2024-02-01"), vec![1]);
+    assert!(linking("Nowhere").is_empty());
+}
+
+#[test]
+fn test_long_dependency_chains() {
+    // Each task waits on the next, and the last one on the first
+    let length = 200_000;
+    let tasks: Vec<Task> = (0..length)
+        .map(|index| Task {
+            name: format!("Step {}", index),
+            id: Some(format!("s{}", index)),
+            depends_on: vec![format!("s{}", (index + 1) % length)],
+            line: Some(index + 1),
+            ..Default::default()
+        })
+        .collect();
+    let refs: Vec<&Task> = tasks.iter().collect();
+
+    let problems = dependencies::check(&refs);
+
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].task, 0);
+    assert!(problems[0]
+        .message
+        .starts_with("dependency cycle s0 ⛔ s1 ⛔ s2 ⛔"));
+    assert!(problems[0].message.ends_with("s199999 ⛔ s0"));
+}
+
+#[test]
+fn test_cancelled_tasks_are_closed() {
+    let tasks = parse_input(
+        "- [-] Dropped plan 🆔 plan\n\
+         - [/] Started 🆔 started\n\
+         - [ ] After the plan ⛔ plan\n\
+         - [ ] After the start ⛔ started",
+    )
+    .tasks;
+    let refs: Vec<&Task> = tasks.iter().collect();
+    let open_ids = dependencies::open_ids(&refs);
+
+    assert!(tasks[0].completed);
+    assert!(!tasks[1].completed);
+    assert!(!dependencies::is_blocked(&tasks[2], &open_ids));
+    assert!(dependencies::is_blocked(&tasks[3], &open_ids));
+    let actionable: Vec<&str> = BlockedFilter {
+        open_ids,
+        show_blocked: false,
+    }
+    .apply(refs)
+    .iter()
+    .map(|task| task.name.as_str())
+    .collect();
+    assert_eq!(actionable, ["Started", "After the plan"]);
+}
+
+#[test]
+fn test_dependencies() {
+    let tasks = parse_input(
+        "- [x] Draft outline 🆔 outline\n\
+         - [ ] Write chapter 🆔 chapter ⛔ outline ⏫ 📅 2024-03-01\n\
+         - [ ] Edit chapter [id:: edit] [dependsOn:: chapter, review]\n\
+         - [ ] Loop one 🆔 one ⛔ two\n\
+         - [ ] Loop two 🆔 two ⛔ one\n\
+         - [ ] Duplicate 🆔 one",
+    )
+    .tasks;
+
+    assert_eq!(tasks[1].name, "Write chapter");
+    assert_eq!(tasks[1].id.as_deref(), Some("chapter"));
+    assert_eq!(tasks[1].depends_on, vec!["outline"]);
+    assert_eq!(tasks[1].priority, Priority::High);
+    assert_eq!(tasks[2].name, "Edit chapter");
+    assert_eq!(tasks[2].depends_on, vec!["chapter", "review"]);
+
+    let refs: Vec<&Task> = tasks.iter().collect();
+    let open_ids = dependencies::open_ids(&refs);
+    let lines = |show_blocked: bool| -> Vec<usize> {
+        BlockedFilter {
+            open_ids: open_ids.clone(),
+            show_blocked,
+        }
+        .apply(refs.clone())
+        .iter()
+        .filter_map(|task| task.line)
+        .collect()
+    };
+    assert_eq!(lines(true), vec![3, 4, 5]);
+    assert_eq!(lines(false), vec![2, 6]);
+
+    let problems: Vec<(usize, String)> = dependencies::check(&refs)
+        .into_iter()
+        .map(|problem| (problem.task, problem.message))
+        .collect();
+    assert_eq!(
+        problems,
+        vec![
+            (2, "depends on unknown task id `review`".to_string()),
+            (3, "dependency cycle one ⛔ two ⛔ one".to_string()),
+            (
+                5,
+                "duplicate task id `one`, first used on line 4".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_times_and_estimates() {
+    let parsed = parse_input(
+        "- [ ] Standup ⏳ 2024-02-10 09:30 ⏱ 15m\n\
+         - [ ] Review [scheduled:: 2024-02-10 14:00] [estimate:: 1h30m]\n\
+         - [ ] Plan 🔁 every week ⏱️ 2h 📅 2024-02-12\n\
+         - [ ] Broken ⏳ 2024-02-10 25:00 ⏱ soon",
+    );
+    let tasks = parsed.tasks;
+
+    assert_eq!(tasks[0].name, "Standup");
+    assert_eq!(tasks[0].scheduled, Some(ymd(2024, 2, 10)));
+    assert_eq!(tasks[0].scheduled_time, NaiveTime::from_hms_opt(9, 30, 0));
+    assert_eq!(
+        tasks[0].scheduled_at(),
+        ymd(2024, 2, 10).and_hms_opt(9, 30, 0)
+    );
+    assert_eq!(tasks[0].estimate, Some(15));
+    assert_eq!(tasks[1].name, "Review");
+    assert_eq!(tasks[1].scheduled_time, NaiveTime::from_hms_opt(14, 0, 0));
+    assert_eq!(tasks[1].estimate, Some(90));
+    assert_eq!(tasks[2].name, "Plan");
+    assert_eq!(tasks[2].recurrence.as_deref(), Some("every week"));
+    assert_eq!(tasks[2].estimate, Some(120));
+    assert_eq!(tasks[2].due_time, None);
+    assert_eq!(tasks[2].due_at(), ymd(2024, 2, 12).and_hms_opt(0, 0, 0));
+
+    let messages: Vec<&str> = parsed
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.message.as_str())
+        .collect();
+    assert_eq!(
+        messages,
+        vec!["invalid scheduled time `25:00`", "invalid estimate `soon`"]
+    );
+
+    let refs: Vec<&Task> = tasks.iter().collect();
+    let scheduled = ScheduledFilter {
+        date: ymd(2024, 2, 10),
+    }
+    .apply(refs);
+    let total: u32 = scheduled.iter().filter_map(|task| task.estimate).sum();
+    assert_eq!(scheduled.len(), 3);
+    assert_eq!(total, 105);
+
+    assert_eq!(
+        markdown::convert(
+            "- [ ] Review [scheduled:: 2024-02-10 14:00] [estimate:: 1h30m]",
+            Syntax::Emoji
+        ),
+        "- [ ] Review ⏳ 2024-02-10 14:00 ⏱ 1h30m"
+    );
+    assert_eq!(
+        markdown::convert("- [ ] Standup ⏳ 2024-02-10 09:30 ⏱ 15m", Syntax::Dataview),
+        "- [ ] Standup [scheduled:: 2024-02-10 09:30] [estimate:: 15m]"
+    );
+}
+
+#[test]
+fn test_mentions() {
+    let tasks = parse_input(
+        "- [ ] Review PR @alice @bob 📅 2024-02-10\n\
+         - [ ] Call the bank @phone\n\
+         - [ ] Email bob@example.com @Alice @alice\n\
+         - [ ] Plan the week",
+    )
+    .tasks;
+
+    assert_eq!(tasks[0].name, "Review PR @alice @bob");
+    assert_eq!(tasks[0].contexts, vec!["alice", "bob"]);
+    assert_eq!(tasks[1].contexts, vec!["phone"]);
+    assert_eq!(tasks[2].contexts, vec!["Alice"]);
+    assert!(tasks[3].contexts.is_empty());
+    assert_eq!(mentions::strip(&tasks[0].name), "Review PR");
+    assert_eq!(mentions::strip(&tasks[2].name), "Email bob@example.com");
+
+    let refs: Vec<&Task> = tasks.iter().collect();
+    let lines = |context: &str| -> Vec<usize> {
+        ContextFilter {
+            context: context.to_string(),
+        }
+        .apply(refs.clone())
+        .iter()
+        .filter_map(|task| task.line)
+        .collect()
+    };
+    assert_eq!(lines("alice"), vec![1, 3]);
+    assert_eq!(lines("@PHONE"), vec![2]);
+    assert!(lines("example.com").is_empty());
+
+    let groups: Vec<(Option<String>, Vec<usize>)> = mentions::group(&refs)
+        .into_iter()
+        .map(|(context, tasks)| (context, tasks.iter().filter_map(|t| t.line).collect()))
+        .collect();
+    assert_eq!(
+        groups,
+        vec![
+            (Some("alice".to_string()), vec![1, 3]),
+            (Some("bob".to_string()), vec![1]),
+            (Some("phone".to_string()), vec![2]),
+            (None, vec![4]),
+        ]
+    );
+}
+
+#[test]
+fn test_urgency() {
+    let tasks = parse_input(
+        "- [ ] Ship ⏫ 📅 2024-02-10 #work #next\n\
+         - [ ] Overdue 📅 2024-01-20\n\
+         - [ ] Waiting 🛫 2024-03-01 ⏳ 2024-02-09\n\
+         - [ ] Old idea ➕ 2023-08-14 🔽\n\
+         - [x] Done ⏫\n\
+         - [ ] Meeting 📅 2024-02-10 11:00",
+    )
+    .tasks;
+    assert_eq!(tasks[3].name, "Old idea");
+    assert_eq!(tasks[3].created, Some(ymd(2023, 8, 14)));
+
+    let now = ymd(2024, 2, 10).and_hms_opt(12, 0, 0).unwrap();
+    let model = UrgencyModel::default();
+    let scores: Vec<f64> = tasks.iter().map(|task| model.score(task, now)).collect();
+    assert_eq!(scores, vec![15.93, 14.0, 2.0, -0.81, 0.0, 10.82]);
+
+    // todo.txt creation dates count towards a task's age too
+    let imported = todotxt::parse("(D) 2023-08-14 Old idea").tasks;
+    assert_eq!(imported[0].created, tasks[3].created);
+    assert_eq!(model.score(&imported[0], now), -0.81);
+
+    let config = Config::from_toml("[urgency]\ndue = 0.0\n\n[urgency.tag]\nnext = 15.0")
+        .expect("Valid configuration");
+    assert_eq!(config.urgency.score(&tasks[0], now), 21.9);
+    assert_eq!(config.urgency.priority, model.priority);
+    assert!(Config::from_toml("[urgency]\nimportance = 1.0").is_err());
+}
+
+#[test]
+fn test_future_tasks_on_the_command_line() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("tests/data/future_tasks.md");
+    let run = |args: &[&str]| -> Vec<String> {
+        let output = Command::new(env!("CARGO_BIN_EXE_marktask"))
+            .args(args)
+            .arg(&path)
+            .output()
+            .expect("Failed to run marktask");
+        assert!(output.status.success(), "marktask {:?} failed", args);
+        String::from_utf8(output.stdout)
+            .expect("Output is not UTF-8")
+            .lines()
+            .map(str::to_string)
+            .collect()
+    };
+
+    // Tasks that have not started are only listed when they are asked for
+    assert_eq!(run(&[]), vec!["[ ] - Now"]);
+    assert_eq!(run(&["--include-future"]).len(), 3);
+    assert_eq!(run(&["--from", "2998-01-01"]), vec!["[ ] - Later"]);
+    assert_eq!(
+        run(&["--to", "2999-12-31"]),
+        vec!["[ ] - Now", "[ ] - Later"]
+    );
+    assert_eq!(run(&["--scheduled", "2999-01-02"]), vec!["[ ] - Planned"]);
+    assert_eq!(run(&["export", "--format", "todotxt"]).len(), 3);
+    assert_eq!(
+        run(&["--format", "todotxt"]),
+        run(&["export", "--format", "todotxt"])
+    );
+    assert_eq!(run(&["next"]).len(), 1);
+}
+
+#[test]
+fn test_closed_stdout_is_not_an_error() {
+    let input =
+        "- [ ] Write a long enough task list to fill the output buffer 📅 2024-02-10\n".repeat(50);
+    for format in ["json", "ndjson", "taskwarrior"] {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_marktask"))
+            .args(["--format", format])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to run marktask");
+        // Close the reading end before anything is written, as `head -c 0` would
+        drop(child.stdout.take());
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(input.as_bytes())
+            .expect("Failed to write input");
+        let output = child
+            .wait_with_output()
+            .expect("Failed to wait for marktask");
+        assert_eq!(output.status.code(), Some(0), "--format {}", format);
+        assert!(output.stderr.is_empty(), "--format {}", format);
+    }
+}
+
+#[test]
+fn test_repeated_names_get_their_own_ids() {
+    let input = "- [ ] Water plants\n- [ ] Water plants\n- [ ] water  plants\n";
+    let run = |args: &[&str]| -> String {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_marktask"))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Failed to run marktask");
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(input.as_bytes())
+            .expect("Failed to write input");
+        let output = child
+            .wait_with_output()
+            .expect("Failed to wait for marktask");
+        assert!(output.status.success(), "marktask {:?} failed", args);
+        String::from_utf8(output.stdout).expect("Output is not UTF-8")
+    };
+    let distinct = |ids: Vec<String>| {
+        assert_eq!(ids.len(), 3, "{:?}", ids);
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), 3, "{:?}", ids);
+        ids
+    };
+
+    let calendar = run(&["--format", "ics"]);
+    let uids = distinct(
+        calendar
+            .lines()
+            .filter_map(|line| line.strip_prefix("UID:"))
+            .map(|uid| uid.trim_end_matches("@marktask").to_string())
+            .collect(),
+    );
+    let export: serde_json::Value =
+        serde_json::from_str(&run(&["--format", "taskwarrior"])).expect("Invalid JSON");
+    distinct(
+        export
+            .as_array()
+            .expect("An array of tasks")
+            .iter()
+            .map(|record| record["uuid"].as_str().expect("A uuid").to_string())
+            .collect(),
+    );
+    // Writing the IDs into the file keeps them
+    let assigned = run(&["assign-ids"]);
+    let block_ids: Vec<String> = assigned
+        .lines()
+        .filter_map(|line| line.rsplit_once(" ^"))
+        .map(|(_, id)| id.to_string())
+        .collect();
+    assert_eq!(block_ids, uids);
+}
+
+#[test]
+fn test_start_dates() {
+    let mut tasks = parse_input(
+        "- [ ] Now\n\
+         - [ ] Later 🛫 2024-03-01 📅 2024-03-05\n\
+         - [ ] Planned ⏳ 2024-02-12\n\
+         - [ ] Started 🛫 2024-02-10 📅 2024-02-10\n\
+         - [ ] Soon 📅 2024-02-13\n\
+         - [x] Done 📅 2024-02-11\n\
+         - [ ] Eventually 📅 2024-02-14",
+    )
+    .tasks;
+    let today = ymd(2024, 2, 10);
+    for task in &mut tasks {
+        task.update_date_flags(today, 3);
+    }
+
+    let flagged = |flag: fn(&Task) -> bool| -> Vec<&str> {
+        tasks
+            .iter()
+            .filter(|task| flag(task))
+            .map(|task| task.name.as_str())
+            .collect()
+    };
+    assert_eq!(flagged(|task| task.not_started), vec!["Later"]);
+    assert_eq!(flagged(|task| task.due_today), vec!["Started"]);
+    assert_eq!(flagged(|task| task.due_soon), vec!["Started", "Soon"]);
+
+    let template = Template::parse("{name}{?not_started} later{/not_started}{?due_today} today{/due_today}{?due_soon} soon{/due_soon}")
+        .expect("Valid template");
+    assert_eq!(template.render(&tasks[1]), "Later later");
+    assert_eq!(template.render(&tasks[3]), "Started today soon");
+    assert_eq!(template.render(&tasks[4]), "Soon soon");
+    let mut buffer = Vec::new();
+    let columns = csv::parse_columns("not_started,due_today,due_soon").expect("Valid columns");
+    csv::write_tasks(
+        &mut buffer,
+        &[&tasks[1], &tasks[3]],
+        &columns,
+        Delimiter::Comma,
+    )
+    .expect("Failed to write");
+    assert_eq!(
+        String::from_utf8(buffer).expect("Output is not UTF-8"),
+        "not_started,due_today,due_soon\r\ntrue,false,false\r\nfalse,true,true\r\n"
+    );
+
+    let refs: Vec<&Task> = tasks.iter().collect();
+    let active: Vec<&str> = ActiveFilter { today }
+        .apply(refs)
+        .iter()
+        .map(|task| task.name.as_str())
+        .collect();
+    assert_eq!(active, vec!["Now", "Started", "Soon", "Done", "Eventually"]);
+}